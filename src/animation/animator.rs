@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use crate::{
+    animation::clip::{normalize_value, AnimationClip, AnimationValue, TrackTarget},
+    entity::EntityId,
+    error::*,
+    event::{OnEventContext, OnStartContext, OnUpdateContext},
+    scene::Scene,
+    Component,
+};
+
+/// Closure run when playback reaches an event of the clip, with the entity of the animator and
+/// the name of the event
+pub type AnimationEventCallback = Box<dyn FnMut(&mut Scene, EntityId, &str) + Send + Sync>;
+
+struct Playback {
+    clip: String,
+    time: f32,
+    // not advanced yet: events at the start time fire on the first advance
+    fresh: bool,
+}
+
+impl Playback {
+    fn new(clip: &str) -> Self {
+        Self {
+            clip: clip.to_string(),
+            time: 0.,
+            fresh: true,
+        }
+    }
+
+    /// Move the playback by `delta` seconds of clip time, returning the events passed on the way
+    fn advance(&mut self, clip: &AnimationClip, delta: f32, looping: bool) -> Vec<String> {
+        let duration = clip.duration();
+        let forward = delta >= 0.;
+        if self.fresh && !forward && self.time == 0. {
+            // playing backwards starts from the end
+            self.time = duration;
+        }
+
+        // clip time ranges covered, from the start of the move to its end
+        let mut ranges = vec![];
+        let outside = if forward {
+            f32::NEG_INFINITY
+        } else {
+            f32::INFINITY
+        };
+        let mut from = if self.fresh { outside } else { self.time };
+        let mut to = self.time + delta;
+        self.fresh = false;
+        if looping && duration > 0. {
+            while to > duration {
+                ranges.push((from, duration));
+                from = outside;
+                to -= duration;
+            }
+            while to < 0. {
+                ranges.push((from, 0.));
+                from = outside;
+                to += duration;
+            }
+        } else {
+            to = to.clamp(0., duration);
+        }
+        ranges.push((from, to));
+        self.time = to;
+
+        let passed = |time: f32, (from, to): (f32, f32)| {
+            if forward {
+                from < time && time <= to
+            } else {
+                to <= time && time < from
+            }
+        };
+        let mut events = vec![];
+        for range in ranges {
+            let in_range = clip.events().iter().filter(|e| passed(e.time, range));
+            if forward {
+                events.extend(in_range.map(|e| e.name.clone()));
+            } else {
+                events.extend(in_range.rev().map(|e| e.name.clone()));
+            }
+        }
+        events
+    }
+}
+
+/// Plays animation clips on its entity: clips drive the local transform of the entity and
+/// the animator's properties, which other components can read.
+pub struct Animator {
+    clips: HashMap<String, AnimationClip>,
+    current: Option<Playback>,
+    // clip being faded out, with the time since the fade started and its length
+    fading: Option<(Playback, f32, f32)>,
+    speed: f32,
+    looping: bool,
+    paused: bool,
+    properties: HashMap<String, AnimationValue>,
+    // events fired during the last update
+    fired_events: Vec<String>,
+    event_callbacks: Vec<AnimationEventCallback>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            clips: HashMap::new(),
+            current: None,
+            fading: None,
+            speed: 1.,
+            looping: true,
+            paused: false,
+            properties: HashMap::new(),
+            fired_events: vec![],
+            event_callbacks: vec![],
+        }
+    }
+
+    /// Add a clip, replacing any clip with the same name
+    pub fn add_clip(&mut self, clip: AnimationClip) {
+        self.clips.insert(clip.name.clone(), clip);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    /// Play a clip from its start, cutting off what was playing
+    pub fn play(&mut self, name: &str) -> Result<()> {
+        if !self.clips.contains_key(name) {
+            return Err(Error::AnimationNotFound(name.to_string()));
+        }
+        self.current = Some(Playback::new(name));
+        self.fading = None;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Play a clip from its start, blending from what was playing over `duration` seconds
+    pub fn crossfade(&mut self, name: &str, duration: f32) -> Result<()> {
+        if !self.clips.contains_key(name) {
+            return Err(Error::AnimationNotFound(name.to_string()));
+        }
+        let previous = self.current.replace(Playback::new(name));
+        self.fading = previous
+            .filter(|_| duration > 0.)
+            .map(|previous| (previous, 0., duration));
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Stop playing. The entity keeps its last pose
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.fading = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Is a clip playing. Clips that don't loop stop playing when they reach their end
+    pub fn is_playing(&self) -> bool {
+        let Some(current) = &self.current else {
+            return false;
+        };
+        if self.paused {
+            return false;
+        }
+        let finished = !self.looping
+            && self.clips.get(&current.clip).is_some_and(|clip| {
+                if self.speed >= 0. {
+                    current.time >= clip.duration()
+                } else {
+                    !current.fresh && current.time <= 0.
+                }
+            });
+        !finished
+    }
+
+    /// Name of the clip playing
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_ref().map(|p| p.clip.as_str())
+    }
+
+    /// Time in the clip playing, in seconds
+    pub fn time(&self) -> f32 {
+        self.current.as_ref().map_or(0., |p| p.time)
+    }
+
+    /// Multiplier of the playback speed, negative values play backwards
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Should clips start over when they reach their end. True by default
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Value of a property animated by a `TrackTarget::Property` track
+    pub fn property(&self, name: &str) -> Option<&AnimationValue> {
+        self.properties.get(name)
+    }
+
+    /// Events reached during the last update, in order
+    pub fn fired_events(&self) -> &[String] {
+        &self.fired_events
+    }
+
+    /// Run `callback` every time playback reaches an event
+    pub fn add_event_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Scene, EntityId, &str) + Send + Sync + 'static,
+    {
+        self.event_callbacks.push(Box::new(callback));
+    }
+
+    /// Advance the playbacks, and get the blended value of every target
+    fn advance(&mut self, delta_time: f32) -> Vec<(TrackTarget, AnimationValue)> {
+        self.fired_events.clear();
+        let Some(current) = &mut self.current else {
+            return vec![];
+        };
+        let Some(clip) = self.clips.get(&current.clip) else {
+            return vec![];
+        };
+        let delta = if self.paused {
+            0.
+        } else {
+            delta_time * self.speed
+        };
+
+        self.fired_events = current.advance(clip, delta, self.looping);
+        let mut values = clip.sample(current.time);
+
+        let Some((previous, elapsed, duration)) = &mut self.fading else {
+            return values;
+        };
+        if !self.paused {
+            *elapsed += delta_time;
+        }
+        let weight = (*elapsed / *duration).min(1.);
+        if let Some(previous_clip) = self.clips.get(&previous.clip) {
+            // the faded out clip keeps playing, without firing its events
+            previous.advance(previous_clip, delta, self.looping);
+            for (target, from) in previous_clip.sample(previous.time) {
+                match values.iter_mut().find(|(t, _)| *t == target) {
+                    Some((_, to)) => *to = normalize_value(from.lerp(to, weight)),
+                    // targets that aren't in the new clip keep their last value
+                    None => values.push((target, from)),
+                }
+            }
+        }
+        if weight >= 1. {
+            self.fading = None;
+        }
+        values
+    }
+}
+
+impl Component for Animator {
+    fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+    fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+        let values = self.advance(context.delta_time.as_secs_f32());
+
+        TrackTarget::apply_values(values, scene, context.entity, &mut self.properties);
+
+        for event in &self.fired_events {
+            for callback in &mut self.event_callbacks {
+                callback(scene, context.entity, event);
+            }
+        }
+    }
+
+    fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use cgmath::{vec3, InnerSpace as _};
+
+    use super::*;
+    use crate::{
+        animation::clip::{Interpolation, Keyframe, Track},
+        entity::transform::TransformComponent,
+        Vector3,
+    };
+
+    fn slide(name: &str, to: Vector3) -> AnimationClip {
+        let mut clip = AnimationClip::new(name);
+        clip.add_track(Track::new(
+            TrackTarget::Position,
+            Interpolation::Linear,
+            vec![
+                Keyframe::new(0., AnimationValue::Vector(vec3(0., 0., 0.))),
+                Keyframe::new(1., AnimationValue::Vector(to)),
+            ],
+        ));
+        clip
+    }
+
+    fn position(scene: &Scene, entity: &EntityId) -> Vector3 {
+        let transform = scene.get_transform(entity).unwrap();
+        scene
+            .get_ref_component::<TransformComponent>(&transform)
+            .unwrap()
+            .position_local()
+    }
+
+    #[test]
+    fn test_animator_drives_transform_and_events() {
+        let mut scene = Scene::new();
+        let entity = scene
+            .add_entity(scene.get_root(), "animated".to_string())
+            .unwrap();
+
+        let mut clip = slide("slide", vec3(4., 0., 0.));
+        clip.add_event(0.5, "halfway");
+        let mut animator = Animator::new();
+        animator.add_clip(clip);
+        animator.play("slide").unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_ref = seen.clone();
+        animator
+            .add_event_callback(move |_, _, name| seen_ref.lock().unwrap().push(name.to_string()));
+        let animator = scene.add_component(entity, animator).unwrap();
+
+        scene.on_update(Duration::from_millis(250)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(1., 0., 0.));
+        assert!(seen.lock().unwrap().is_empty());
+
+        scene.on_update(Duration::from_millis(500)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(3., 0., 0.));
+        assert_eq!(*seen.lock().unwrap(), vec!["halfway".to_string()]);
+
+        // loops back to the start, passing the event again
+        scene.on_update(Duration::from_millis(1000)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(3., 0., 0.));
+        assert_eq!(seen.lock().unwrap().len(), 2);
+
+        let animator = scene.get_mut_component::<Animator>(&animator).unwrap();
+        assert!(animator.play("missing").is_err());
+        animator.set_looping(false);
+        scene.on_update(Duration::from_millis(1000)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(4., 0., 0.));
+    }
+
+    #[test]
+    fn test_animator_crossfade() {
+        let mut scene = Scene::new();
+        let entity = scene
+            .add_entity(scene.get_root(), "animated".to_string())
+            .unwrap();
+
+        let mut animator = Animator::new();
+        animator.add_clip(slide("right", vec3(4., 0., 0.)));
+        animator.add_clip(slide("up", vec3(0., 4., 0.)));
+        animator.set_looping(false);
+        animator.play("right").unwrap();
+        let animator = scene.add_component(entity, animator).unwrap();
+        scene.on_update(Duration::from_millis(500)).unwrap();
+
+        scene
+            .get_mut_component::<Animator>(&animator)
+            .unwrap()
+            .crossfade("up", 1.)
+            .unwrap();
+        // half way through the fade: (4, 0, 0) -> (0, 2, 0) at weight 0.5
+        scene.on_update(Duration::from_millis(500)).unwrap();
+        let p = position(&scene, &entity);
+        assert!((p - vec3(2., 1., 0.)).magnitude() < 1e-4, "{p:?}");
+
+        scene.on_update(Duration::from_millis(500)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(0., 4., 0.));
+    }
+}
@@ -0,0 +1,358 @@
+use cgmath::{InnerSpace as _, VectorSpace as _};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::{entity::EntityId, error::*, scene::Scene, Quaternion, Vector3};
+
+/// How values are interpolated between two keyframes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Hold the value of the previous keyframe
+    Step,
+    #[default]
+    Linear,
+    /// Smooth curve going through every keyframe (Catmull-Rom).
+    /// Rotations are interpolated linearly, along the shortest arc.
+    Cubic,
+}
+
+/// What a track animates
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TrackTarget {
+    /// Local position of the entity, animated with `AnimationValue::Vector`
+    Position,
+    /// Local rotation of the entity, animated with `AnimationValue::Rotation`
+    Rotation,
+    /// Local scale of the entity, animated with `AnimationValue::Vector`
+    Scale,
+    /// A named property of the animator, read by other components with `Animator::property`
+    Property(String),
+}
+
+impl TrackTarget {
+    /// Apply sampled values: transform targets to the local transform of the entity, and
+    /// properties into `properties`. Values of the wrong kind for their target are ignored
+    pub(crate) fn apply_values(
+        values: Vec<(TrackTarget, AnimationValue)>,
+        scene: &mut Scene,
+        entity: EntityId,
+        properties: &mut HashMap<String, AnimationValue>,
+    ) {
+        let targets_transform = values
+            .iter()
+            .any(|(t, _)| !matches!(t, TrackTarget::Property(_)));
+        if targets_transform {
+            if let Ok(transform) = scene.get_mut_transform(&entity) {
+                for (target, value) in &values {
+                    match (target, value) {
+                        (TrackTarget::Position, AnimationValue::Vector(v)) => {
+                            transform.set_position_local(*v)
+                        }
+                        (TrackTarget::Rotation, AnimationValue::Rotation(q)) => {
+                            transform.set_rotation_local(*q)
+                        }
+                        (TrackTarget::Scale, AnimationValue::Vector(v)) => {
+                            transform.set_scale_local(*v)
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        for (target, value) in values {
+            if let TrackTarget::Property(name) = target {
+                properties.insert(name, value);
+            }
+        }
+    }
+}
+
+/// A value taken by a track
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AnimationValue {
+    Float(f32),
+    Vector(#[serde(with = "crate::types::serde_array::vector3")] Vector3),
+    Rotation(#[serde(with = "crate::types::serde_array::quaternion")] Quaternion),
+}
+
+impl AnimationValue {
+    /// Interpolate linearly between two values: `t` is 0 at `self` and 1 at `other`.
+    /// Values of different kinds can't be interpolated, `self` is kept.
+    pub fn lerp(&self, other: &AnimationValue, t: f32) -> AnimationValue {
+        match (self, other) {
+            (Self::Float(a), Self::Float(b)) => Self::Float(a + (b - a) * t),
+            (Self::Vector(a), Self::Vector(b)) => Self::Vector(a.lerp(*b, t)),
+            (Self::Rotation(a), Self::Rotation(b)) => Self::Rotation(a.slerp(*b, t)),
+            _ => *self,
+        }
+    }
+
+    /// Catmull-Rom interpolation between `p1` and `p2`, using their neighbours `p0` and `p3`
+    fn cubic(
+        p0: &AnimationValue,
+        p1: &AnimationValue,
+        p2: &AnimationValue,
+        p3: &AnimationValue,
+        t: f32,
+    ) -> AnimationValue {
+        let (t2, t3) = (t * t, t * t * t);
+        // weights of each point
+        let w0 = -0.5 * t3 + t2 - 0.5 * t;
+        let w1 = 1.5 * t3 - 2.5 * t2 + 1.;
+        let w2 = -1.5 * t3 + 2. * t2 + 0.5 * t;
+        let w3 = 0.5 * t3 - 0.5 * t2;
+        match (p0, p1, p2, p3) {
+            (Self::Float(a), Self::Float(b), Self::Float(c), Self::Float(d)) => {
+                Self::Float(a * w0 + b * w1 + c * w2 + d * w3)
+            }
+            (Self::Vector(a), Self::Vector(b), Self::Vector(c), Self::Vector(d)) => {
+                Self::Vector(a * w0 + b * w1 + c * w2 + d * w3)
+            }
+            _ => p1.lerp(p2, t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Time of the keyframe from the start of the clip, in seconds
+    pub time: f32,
+    pub value: AnimationValue,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, value: AnimationValue) -> Self {
+        Self { time, value }
+    }
+}
+
+/// Keyframes animating a single target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub target: TrackTarget,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(
+        target: TrackTarget,
+        interpolation: Interpolation,
+        keyframes: Vec<Keyframe>,
+    ) -> Self {
+        let mut track = Self {
+            target,
+            interpolation,
+            keyframes,
+        };
+        track.sort();
+        track
+    }
+
+    fn sort(&mut self) {
+        self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |k| k.time)
+    }
+
+    /// Get the value of the track at `time`. The first and last keyframes are held before and
+    /// after the track. None if the track has no keyframes.
+    pub fn sample(&self, time: f32) -> Option<AnimationValue> {
+        let keys = &self.keyframes;
+        // index of the first keyframe after `time`
+        let next = keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return keys.first().map(|k| k.value);
+        }
+        if next == keys.len() {
+            return keys.last().map(|k| k.value);
+        }
+
+        let (a, b) = (&keys[next - 1], &keys[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        let value = match self.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => a.value.lerp(&b.value, t),
+            Interpolation::Cubic => {
+                // past the ends, continue the line between the last two keyframes
+                let before = match next {
+                    1 => a.value.lerp(&b.value, -1.),
+                    _ => keys[next - 2].value,
+                };
+                let after = match keys.get(next + 1) {
+                    Some(after) => after.value,
+                    None => b.value.lerp(&a.value, -1.),
+                };
+                AnimationValue::cubic(&before, &a.value, &b.value, &after, t)
+            }
+        };
+        Some(value)
+    }
+}
+
+/// A named event, fired when playback reaches its time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+/// A set of tracks played together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Length of the clip in seconds, at least the time of the last keyframe
+    #[serde(default)]
+    duration: f32,
+    #[serde(default)]
+    tracks: Vec<Track>,
+    #[serde(default)]
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationClip {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            duration: 0.,
+            tracks: vec![],
+            events: vec![],
+        }
+    }
+
+    /// Load a clip from RON, see the fields of `AnimationClip`
+    pub fn from_ron(text: &str) -> Result<Self> {
+        let mut clip: AnimationClip = ron::from_str(text)?;
+        for track in &mut clip.tracks {
+            track.sort();
+        }
+        clip.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        clip.duration = clip
+            .tracks
+            .iter()
+            .fold(clip.duration, |d, t| d.max(t.duration()));
+        Ok(clip)
+    }
+
+    pub fn add_track(&mut self, track: Track) {
+        self.duration = self.duration.max(track.duration());
+        self.tracks.push(track);
+    }
+
+    pub fn add_event(&mut self, time: f32, name: impl Into<String>) {
+        self.duration = self.duration.max(time);
+        self.events.push(AnimationEvent {
+            time,
+            name: name.into(),
+        });
+        self.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    /// Make the clip last longer than its last keyframe
+    pub fn set_duration(&mut self, duration: f32) {
+        self.duration = self
+            .tracks
+            .iter()
+            .fold(duration, |d, t| d.max(t.duration()));
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// Get the value of every track at `time`
+    pub fn sample(&self, time: f32) -> Vec<(TrackTarget, AnimationValue)> {
+        self.tracks
+            .iter()
+            .filter_map(|t| Some((t.target.clone(), t.sample(time)?)))
+            .collect()
+    }
+}
+
+/// Normalize a rotation coming out of a blend
+pub(crate) fn normalize_value(value: AnimationValue) -> AnimationValue {
+    match value {
+        AnimationValue::Rotation(q) => AnimationValue::Rotation(q.normalize()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::*;
+
+    #[test]
+    fn test_track_interpolation() {
+        let keys = vec![
+            Keyframe::new(0., AnimationValue::Float(0.)),
+            Keyframe::new(1., AnimationValue::Float(2.)),
+            Keyframe::new(2., AnimationValue::Float(4.)),
+        ];
+        let target = TrackTarget::Property("weight".to_string());
+
+        let step = Track::new(target.clone(), Interpolation::Step, keys.clone());
+        assert_eq!(step.sample(0.5), Some(AnimationValue::Float(0.)));
+
+        let linear = Track::new(target.clone(), Interpolation::Linear, keys.clone());
+        assert_eq!(linear.sample(0.5), Some(AnimationValue::Float(1.)));
+        // held outside of the keyframes
+        assert_eq!(linear.sample(-1.), Some(AnimationValue::Float(0.)));
+        assert_eq!(linear.sample(3.), Some(AnimationValue::Float(4.)));
+
+        // evenly spaced keys on a line stay on the line
+        let cubic = Track::new(target, Interpolation::Cubic, keys);
+        let Some(AnimationValue::Float(v)) = cubic.sample(1.5) else {
+            panic!("expected a float");
+        };
+        approx::assert_abs_diff_eq!(v, 3., epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_clip_from_ron() {
+        let clip = AnimationClip::from_ron(
+            r#"(
+                name: "bounce",
+                tracks: [
+                    (
+                        target: Position,
+                        keyframes: [
+                            (time: 1.0, value: Vector((0.0, 1.0, 0.0))),
+                            (time: 0.0, value: Vector((0.0, 0.0, 0.0))),
+                        ],
+                    ),
+                ],
+                events: [(time: 0.5, name: "peak")],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(clip.duration(), 1.);
+        assert_eq!(clip.events()[0].name, "peak");
+        assert_eq!(
+            clip.sample(0.25),
+            vec![(
+                TrackTarget::Position,
+                AnimationValue::Vector(vec3(0., 0.25, 0.))
+            )]
+        );
+    }
+}
@@ -0,0 +1,6 @@
+/// Module for keyframe animation
+mod animator;
+mod clip;
+
+pub use animator::*;
+pub use clip::*;
@@ -1,4 +1,5 @@
 use cgmath::{Matrix4, Zero};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     event::{OnEventContext, OnStartContext, OnUpdateContext},
@@ -8,10 +9,13 @@ use crate::{
 
 use super::{component::Component, scene::Scene};
 
-#[derive(Clone)]
+/// Serializing a camera only keeps its settings: the matrix and aspect are recalculated
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Camera {
+    #[serde(skip, default = "Matrix4::zero")]
     view_projection_matrix: cgmath::Matrix4<f32>,
 
+    #[serde(skip, default = "default_aspect")]
     aspect: f32,
     fovy: f32,
     znear: f32,
@@ -44,15 +48,21 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_co
     cgmath::Vector4::new(0.0, 0.0, 0.5, 1.0),
 );
 
+fn default_aspect() -> f32 {
+    1.0
+}
+
 impl Component for Camera {
     fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
 
     fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
         // update projection matrix from entity's transform
-        let camera_transform = scene.get_mut_transform(&context.entity);
+        let Ok(camera_transform) = scene.global_transform(&context.entity) else {
+            return;
+        };
         let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
         self.view_projection_matrix =
-            OPENGL_TO_WGPU_MATRIX * proj * camera_transform.global().inverse().as_matrix();
+            OPENGL_TO_WGPU_MATRIX * proj * camera_transform.inverse().as_matrix();
     }
 
     fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
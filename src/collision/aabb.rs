@@ -1,13 +1,16 @@
-use cgmath::{vec3, InnerSpace};
+use cgmath::{vec3, ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
 
-use crate::{transform::Transform, Component, Vector3};
+use crate::{collision::query::Ray, transform::Transform, Component, Vector3};
 
 /// Axis-aligned bounding box : fast and simple
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AxisAlignedBoundingBox {
     // the minimum x, y, and z positions
+    #[serde(with = "crate::types::serde_array::vector3")]
     pub min: Vector3,
     // the maximum x, y, and z positions
+    #[serde(with = "crate::types::serde_array::vector3")]
     pub max: Vector3,
 }
 
@@ -16,6 +19,32 @@ impl AxisAlignedBoundingBox {
         Self { min, max }
     }
 
+    /// Get the box scaled by `scale` along each axis, around the origin
+    pub fn scaled(&self, scale: Vector3) -> Self {
+        let min = self.min.mul_element_wise(scale);
+        let max = self.max.mul_element_wise(scale);
+        Self {
+            min: vec3(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: vec3(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        }
+    }
+
+    /// Minimum and maximum corners in the world. The box stays aligned with the world axis:
+    /// the rotation of the transform is ignored
+    pub(crate) fn world_bounds(&self, transform: &Transform) -> (Vector3, Vector3) {
+        let scaled = self.scaled(transform.scale());
+        (
+            transform.translation() + scaled.min,
+            transform.translation() + scaled.max,
+        )
+    }
+
+    /// Distance along the ray to the box and normal of the face hit, None if it's missed
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        let (min, max) = self.world_bounds(transform);
+        ray_box(ray.origin, ray.direction, min, max)
+    }
+
     /// Get if the two boxes are overlapping
     pub fn contains_aabb(
         &self,
@@ -34,7 +63,8 @@ impl AxisAlignedBoundingBox {
         b: &AxisAlignedBoundingBox,
         b_transform: &Transform,
     ) -> Option<Vector3> {
-        // TODO this doesn't take into account scale!
+        let a = a.scaled(a_transform.scale());
+        let b = b.scaled(b_transform.scale());
         let a_pos = a_transform.translation() + a.center();
         let b_pos = b_transform.translation() + b.center();
         let a_min = a_transform.translation() + a.min;
@@ -100,6 +130,44 @@ impl Component for AxisAlignedBoundingBox {
     fn on_event(&mut self, _scene: &mut crate::Scene, _context: crate::OnEventContext) {}
 }
 
+/// Slab test of a ray against a box: distance to the box and normal of the face hit.
+/// A ray starting inside of the box hits it right away, facing the ray
+pub(crate) fn ray_box(
+    origin: Vector3,
+    direction: Vector3,
+    min: Vector3,
+    max: Vector3,
+) -> Option<(f32, Vector3)> {
+    let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+    let (mut near, mut far) = (0f32, f32::INFINITY);
+    let mut normal = -direction;
+    for i in 0..3 {
+        if direction[i].abs() < f32::EPSILON {
+            // parallel to the slab: must already be between its faces
+            if origin[i] < min[i] || origin[i] > max[i] {
+                return None;
+            }
+            continue;
+        }
+        let t_min = (min[i] - origin[i]) / direction[i];
+        let t_max = (max[i] - origin[i]) / direction[i];
+        let (enter, exit, face) = if t_min <= t_max {
+            (t_min, t_max, -axes[i])
+        } else {
+            (t_max, t_min, axes[i])
+        };
+        if enter > near {
+            near = enter;
+            normal = face;
+        }
+        far = far.min(exit);
+        if near > far {
+            return None;
+        }
+    }
+    Some((near, normal))
+}
+
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -1,111 +1,923 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::InnerSpace;
+
 use crate::{
-    Collider, Scene, component::{ComponentId, ComponentStore}, entity::{EntityId, transform::TransformComponent}
+    collision::{
+        broadphase::{sweep_and_prune, Bounds, Bvh},
+        contact::{CollisionEvent, ContactTracker},
+        query::{Overlap, QueryFilter, Ray, RayHit},
+        sweep::{convex_pieces, time_of_impact},
+        trigger::{TriggerEvent, TriggerTracker},
+    },
+    component::{ComponentId, ComponentStore},
+    entity::{transform::TransformComponent, EntityId},
+    transform::Transform,
+    Collider, ColliderShape, Scene, Vector3,
 };
 
 pub struct CollisionArena {
     static_colliders: Vec<ColliderInfo>,
     dynamic_colliders: Vec<ColliderInfo>,
+    trigger_colliders: Vec<ColliderInfo>,
+    contacts: ContactTracker,
+    triggers: TriggerTracker,
+    /// Hierarchy of the static colliders, None when it must be rebuilt
+    static_bvh: Option<Bvh>,
+    // bounds of the static colliders when the hierarchy was built
+    static_bounds: Vec<(usize, Bounds)>,
+    /// Hierarchy of the dynamic colliders, then the triggers, where the last pass left them.
+    /// None when it must be rebuilt
+    moving_bvh: Option<Bvh>,
+    // colliders of each entity, by list and index, when the hierarchies were built
+    entity_colliders: HashMap<EntityId, Vec<(usize, usize)>>,
+    /// Entities moved, or whose colliders were mutated, since the last pass: the hierarchies
+    /// hold stale bounds for them
+    moved: HashSet<EntityId>,
+    // where the last pass left the continuous colliders, by transform
+    swept_from: HashMap<ComponentId, Transform>,
+    /// Colliders mutated since the last pass, which may have to change of list
+    changed: HashSet<ComponentId>,
+}
+
+// collider component, it's entity, and the transform component associated
+struct ColliderInfo(ComponentId, EntityId, ComponentId);
+
+/// The collider component, None if it was removed or is disabled
+fn enabled_collider<'a>(components: &'a ComponentStore, id: &ComponentId) -> Option<&'a Collider> {
+    components
+        .get_ref::<Collider>(id)
+        .filter(|collider| collider.is_enabled())
+}
+
+/// World bounds of the collider, None if it's disabled or has no transform
+fn world_bounds(info: &ColliderInfo, components: &ComponentStore) -> Option<Bounds> {
+    let ColliderInfo(collider, _, transform) = info;
+    let collider = enabled_collider(components, collider)?;
+    let transform = components.get_ref::<TransformComponent>(transform)?;
+    Some(collider.shape().world_bounds(&transform.global()))
+}
+
+/// World bounds of the colliders, with their index. Disabled colliders, and colliders without
+/// a transform, are left out
+fn bounds_of(colliders: &[ColliderInfo], components: &ComponentStore) -> Vec<(usize, Bounds)> {
+    colliders
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, info)| Some((idx, world_bounds(info, components)?)))
+        .collect()
 }
 
-// collider, it's entity, and the transform component associated
-struct ColliderInfo(Collider, EntityId, ComponentId);
+/// Index of the list the collider goes in: static, dynamic or trigger
+fn list_index(collider: &Collider) -> usize {
+    if collider.is_trigger() {
+        2
+    } else if collider.dynamic() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Passes over the overlapping pairs, at most, to separate colliders pushed into others
+const MAX_SOLVER_ITERATIONS: usize = 16;
+/// Overlaps shallower than this end the passes early
+const PENETRATION_TOLERANCE: f32 = 1e-3;
+
+/// Move `a` and `b` out of each other, each by a share of the correction vector proportional
+/// to its weight, its inverse mass. Returns the correction vector if they collided
+fn correct_pair(
+    components: &mut ComponentStore,
+    a: &ColliderInfo,
+    b: &ColliderInfo,
+    weights: (f32, f32),
+) -> Option<Vector3> {
+    let ColliderInfo(col_a, a, a_trans) = a;
+    let ColliderInfo(col_b, b, b_trans) = b;
+    // an entity cannot collide with itself
+    if a == b || a_trans == b_trans {
+        return None;
+    }
+    let (col_a, col_b) = (
+        enabled_collider(components, col_a)?,
+        enabled_collider(components, col_b)?,
+    );
+    if !col_a.interacts_with(col_b) {
+        return None;
+    }
+    let a_global = components.get_ref::<TransformComponent>(a_trans)?.global();
+    let b_global = components.get_ref::<TransformComponent>(b_trans)?.global();
+    let vec = Collider::get_correction_vec(col_a, &a_global, col_b, &b_global)?;
+
+    let (a_trans, b_trans) = components
+        .get_mut_disjoint_2::<TransformComponent, TransformComponent>([a_trans, b_trans])
+        .ok()?;
+    let (a_weight, b_weight) = weights;
+    let total = a_weight + b_weight;
+    // two immovable colliders still touch, but stay where they are
+    if total > 0. {
+        if a_weight > 0. {
+            a_trans.translate_global(vec * (a_weight / total));
+        }
+        if b_weight > 0. {
+            b_trans.translate_global(-vec * (b_weight / total));
+        }
+    }
+    Some(vec)
+}
 
 impl CollisionArena {
     pub fn new() -> Self {
         Self {
             static_colliders: vec![],
             dynamic_colliders: vec![],
+            trigger_colliders: vec![],
+            contacts: ContactTracker::new(),
+            triggers: TriggerTracker::new(),
+            static_bvh: None,
+            static_bounds: vec![],
+            moving_bvh: None,
+            entity_colliders: HashMap::new(),
+            moved: HashSet::new(),
+            swept_from: HashMap::new(),
+            changed: HashSet::new(),
         }
     }
 
-    pub fn collider_pass(&mut self, components: &mut ComponentStore) {
-        let num_dynamic = self.dynamic_colliders.len();
-        let num_static = self.static_colliders.len();
-        if !(num_dynamic >= 1 && (num_static + num_dynamic) >= 2) {
-            // no collisions to happen
+    /// Push overlapping colliders apart, returning the entities that were moved.
+    /// The collisions found are available from `events` until the next pass, and so are the
+    /// trigger overlaps, found once colliders have been pushed apart
+    pub fn collider_pass(
+        &mut self,
+        components: &mut ComponentStore,
+        inverse_masses: &HashMap<EntityId, f32>,
+    ) -> Vec<EntityId> {
+        self.sort_changed(components);
+        let mut moved = self.sweep_continuous(components);
+        moved.extend(self.resolve_overlaps(components, inverse_masses));
+        self.swept_from = self
+            .dynamic_colliders
+            .iter()
+            .filter(|ColliderInfo(collider, ..)| {
+                enabled_collider(components, collider).is_some_and(Collider::is_continuous)
+            })
+            .filter_map(|ColliderInfo(_, _, transform)| {
+                let global = components
+                    .get_ref::<TransformComponent>(transform)?
+                    .global();
+                Some((transform.clone(), global))
+            })
+            .collect();
+        self.contacts.finish_pass();
+        self.detect_trigger_overlaps(components);
+        self.triggers.finish_pass();
+        self.index_colliders(components);
+        moved
+    }
+
+    /// Collisions found by the last pass, with the entity receiving each of them
+    pub(crate) fn events(&self) -> &[(EntityId, CollisionEvent)] {
+        self.contacts.events()
+    }
+
+    /// Trigger overlaps found by the last pass, with the entity receiving each of them
+    pub(crate) fn trigger_events(&self) -> &[(EntityId, TriggerEvent)] {
+        self.triggers.events()
+    }
+
+    /// Entities overlapping the triggers of an entity during the last pass
+    pub(crate) fn trigger_contents(&self, entity: &EntityId) -> Vec<EntityId> {
+        self.triggers.contents(entity)
+    }
+
+    /// Check the triggers against the solid colliders whose bounds they overlap, without
+    /// moving anything
+    fn detect_trigger_overlaps(&mut self, components: &ComponentStore) {
+        if self.trigger_colliders.is_empty() {
             return;
         }
+        let dynamic_bounds = bounds_of(&self.dynamic_colliders, components);
+        for (t_idx, t_bounds) in bounds_of(&self.trigger_colliders, components) {
+            let ColliderInfo(trigger, t, t_trans) = &self.trigger_colliders[t_idx];
+            let trigger = enabled_collider(components, trigger).unwrap();
+            let t_trans = components
+                .get_ref::<TransformComponent>(t_trans)
+                .unwrap()
+                .global();
 
-        for (a_idx, a_collider) in self.dynamic_colliders.iter().enumerate() {
-            let ColliderInfo(col_a, a, a_trans) = a_collider;
+            let statics = self
+                .static_bvh
+                .as_ref()
+                .map_or(vec![], |bvh| bvh.query(&t_bounds));
+            let dynamics = dynamic_bounds
+                .iter()
+                .filter(|(_, b)| b.overlaps(&t_bounds))
+                .map(|(idx, _)| *idx);
+            let candidates = statics
+                .into_iter()
+                .map(|idx| &self.static_colliders[idx])
+                .chain(dynamics.map(|idx| &self.dynamic_colliders[idx]));
 
-            for b_collider in self.dynamic_colliders.iter().skip(a_idx + 1) {
-                let ColliderInfo(col_b, b, b_trans) = b_collider;
-                // an entity cannot collide with itself
-                if a == b || a_trans == b_trans{
+            for ColliderInfo(other, o, o_trans) in candidates {
+                let Some(other) = enabled_collider(components, other) else {
                     continue;
+                };
+                // an entity cannot be inside its own trigger
+                if t == o || !trigger.interacts_with(other) {
+                    continue;
+                }
+                let o_trans = components.get_ref::<TransformComponent>(o_trans).unwrap();
+                if trigger.contains(&t_trans, other, &o_trans.global()) {
+                    self.triggers.record(*t, *o);
                 }
+            }
+        }
+    }
 
-                let (a_trans, b_trans) = components
-                    .get_mut_disjoint_2::<TransformComponent, TransformComponent>([
-                        &a_trans, &b_trans,
-                    ]);
-                let a_trans = a_trans.unwrap();
-                let b_trans = b_trans.unwrap();
-
-                let vec = Collider::get_correction_vec(
-                    &col_a,
-                    &a_trans.global(),
-                    &col_b,
-                    &b_trans.global(),
-                );
+    /// Rebuild the hierarchy of static colliders if one of them moved, or if they changed
+    fn refresh_static_bvh(&mut self, components: &ComponentStore) {
+        let bounds = bounds_of(&self.static_colliders, components);
+        if self.static_bvh.is_some() && bounds == self.static_bounds {
+            return;
+        }
+        self.static_bvh = Some(Bvh::new(bounds.clone()));
+        self.static_bounds = bounds;
+    }
+
+    /// Build the hierarchy of the dynamic colliders and triggers where the pass left them, for
+    /// the queries until the next pass, see `candidates`
+    fn index_colliders(&mut self, components: &ComponentStore) {
+        // the triggers come after the dynamic colliders
+        let triggers_from = self.dynamic_colliders.len();
+        let mut items = bounds_of(&self.dynamic_colliders, components);
+        items.extend(
+            bounds_of(&self.trigger_colliders, components)
+                .into_iter()
+                .map(|(idx, b)| (triggers_from + idx, b)),
+        );
+        self.moving_bvh = Some(Bvh::new(items));
+
+        let mut entity_colliders = HashMap::<_, Vec<_>>::new();
+        for (list, colliders) in self.lists().into_iter().enumerate() {
+            for (idx, ColliderInfo(_, entity, _)) in colliders.iter().enumerate() {
+                entity_colliders
+                    .entry(*entity)
+                    .or_default()
+                    .push((list, idx));
+            }
+        }
+        self.entity_colliders = entity_colliders;
+        self.moved.clear();
+    }
+
+    /// Cast the continuous colliders from where the last pass left them to where they are,
+    /// moving them back to the first collider in the way. Returns the entities moved
+    fn sweep_continuous(&mut self, components: &mut ComponentStore) -> Vec<EntityId> {
+        let mut moved = vec![];
+        for ColliderInfo(collider, entity, transform_id) in &self.dynamic_colliders {
+            let Some(collider) = enabled_collider(components, collider) else {
+                continue;
+            };
+            if !collider.is_continuous() {
+                continue;
+            }
+            let (Some(start), Some(end)) = (
+                self.swept_from.get(transform_id),
+                components.get_ref::<TransformComponent>(transform_id),
+            ) else {
+                continue;
+            };
+            let end = end.global();
+            let travel = end.translation() - start.translation();
+            let distance = travel.magnitude();
+            // the overlap tests can't miss anything on shorter moves
+            if distance <= collider.shape().thickness(&end) / 2. {
+                continue;
+            }
+            let direction = travel / distance;
+            // what the collider already overlaps at the start is left to the overlap tests
+            let hit = self.cast_shape(
+                components,
+                collider.shape(),
+                &(Transform::from_translation(-travel) * end),
+                direction,
+                distance,
+                |other, other_entity| {
+                    other_entity != entity && !other.is_trigger() && collider.interacts_with(other)
+                },
+                true,
+            );
+            let Some(hit) = hit else {
+                continue;
+            };
+            components
+                .get_mut::<TransformComponent>(transform_id)
+                .unwrap()
+                .translate_global(-direction * (distance - hit.distance));
+            self.contacts
+                .record_touch(*entity, hit.entity, hit.normal, 0.);
+            moved.push(*entity);
+        }
+        moved
+    }
 
-                match vec {
-                    Some(vec) => {
-                        // a and b are both dynamic
-                        a_trans.translate_global(vec / 2.);
-                        b_trans.translate_global(-vec / 2.);
+    /// Push the dynamic colliders out of the others, pass after pass over the overlapping
+    /// pairs, so that a collider pushed into a third one is moved out again. Dynamic colliders
+    /// move in proportion to their inverse mass from `inverse_masses`, 1 if their entity isn't
+    /// in it. Returns the entities moved
+    fn resolve_overlaps(
+        &mut self,
+        components: &mut ComponentStore,
+        inverse_masses: &HashMap<EntityId, f32>,
+    ) -> Vec<EntityId> {
+        let mut moved = vec![];
+        self.refresh_static_bvh(components);
+        if self.dynamic_colliders.is_empty() {
+            // no collisions to happen
+            return moved;
+        }
+
+        for _ in 0..MAX_SOLVER_ITERATIONS {
+            let deepest = self.resolve_iteration(components, inverse_masses, &mut moved);
+            if deepest < PENETRATION_TOLERANCE {
+                break;
+            }
+        }
+        moved.sort();
+        moved.dedup();
+        moved
+    }
+
+    /// One pass over the overlapping pairs, returning the deepest overlap corrected
+    fn resolve_iteration(
+        &mut self,
+        components: &mut ComponentStore,
+        inverse_masses: &HashMap<EntityId, f32>,
+        moved: &mut Vec<EntityId>,
+    ) -> f32 {
+        let weight_of = |entity: &EntityId| inverse_masses.get(entity).copied().unwrap_or(1.);
+        let mut deepest = 0f32;
+
+        // broadphase: only pairs whose bounds overlap can collide
+        let dynamic_bounds = bounds_of(&self.dynamic_colliders, components);
+        let mut dynamic_pairs = vec![vec![]; self.dynamic_colliders.len()];
+        for (a_idx, b_idx) in sweep_and_prune(&dynamic_bounds) {
+            dynamic_pairs[a_idx].push(b_idx);
+        }
+        let static_bvh = self.static_bvh.as_ref().unwrap();
+
+        for (a_idx, a_bounds) in dynamic_bounds {
+            let a_collider = &self.dynamic_colliders[a_idx];
+            let a_weight = weight_of(&a_collider.1);
+            for b_idx in &dynamic_pairs[a_idx] {
+                let b_collider = &self.dynamic_colliders[*b_idx];
+                let weights = (a_weight, weight_of(&b_collider.1));
+                if let Some(vec) = correct_pair(components, a_collider, b_collider, weights) {
+                    for (entity, weight) in [(a_collider.1, weights.0), (b_collider.1, weights.1)] {
+                        if weight > 0. {
+                            moved.push(entity);
+                        }
                     }
-                    // no collision
-                    None => continue,
+                    deepest = deepest.max(vec.magnitude());
+                    self.contacts.record(a_collider.1, b_collider.1, vec);
                 }
             }
-
-            for b_collider in self.static_colliders.iter() {
-                let ColliderInfo(col_b, b, b_trans) = b_collider;
-                // an entity cannot collide with itself
-                if a == b || a_trans == b_trans{
-                    continue;
+            for b_idx in static_bvh.query(&a_bounds) {
+                let b_collider = &self.static_colliders[b_idx];
+                // statics don't move
+                let weights = (a_weight, 0.);
+                if let Some(vec) = correct_pair(components, a_collider, b_collider, weights) {
+                    if a_weight > 0. {
+                        moved.push(a_collider.1);
+                    }
+                    deepest = deepest.max(vec.magnitude());
+                    self.contacts.record(a_collider.1, b_collider.1, vec);
                 }
+            }
+        }
+        deepest
+    }
+
+    /// Every collider hit by the ray within `max_distance`, closest first. Candidates are
+    /// found by their bounds, see `candidates`
+    pub(crate) fn raycast_all(
+        &self,
+        components: &ComponentStore,
+        ray: &Ray,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Vec<RayHit> {
+        // only colliders within the bounds of the segment can be hit
+        let end = ray.point_at(max_distance.min(f32::MAX));
+        let bounds = Bounds::around([ray.origin, end]);
+
+        let mut hits = vec![];
+        for ColliderInfo(collider, entity, transform) in self.candidates(components, &bounds) {
+            let Some(collider) = enabled_collider(components, collider) else {
+                continue;
+            };
+            if !filter.accepts(collider, entity) {
+                continue;
+            }
+            let Some(transform) = components.get_ref::<TransformComponent>(transform) else {
+                continue;
+            };
+            let Some((distance, normal)) =
+                collider.shape().ray_intersection(&transform.global(), ray)
+            else {
+                continue;
+            };
+            if distance <= max_distance {
+                hits.push(RayHit {
+                    entity: *entity,
+                    distance,
+                    point: ray.point_at(distance),
+                    normal,
+                });
+            }
+        }
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// Colliders overlapping `shape` at `transform`. Candidates are found by their bounds,
+    /// see `candidates`
+    pub(crate) fn overlap(
+        &self,
+        components: &ComponentStore,
+        shape: &ColliderShape,
+        transform: &Transform,
+        filter: &QueryFilter,
+    ) -> Vec<Overlap> {
+        let bounds = shape.world_bounds(transform);
+        let candidates = self.candidates(components, &bounds);
+
+        let mut found = vec![];
+        for ColliderInfo(id, entity, other) in candidates {
+            let Some(collider) = enabled_collider(components, id) else {
+                continue;
+            };
+            if !filter.accepts(collider, entity) {
+                continue;
+            }
+            let Some(other) = components.get_ref::<TransformComponent>(other) else {
+                continue;
+            };
+            let other = other.global();
+            if ColliderShape::get_correction_vec(shape, transform, collider.shape(), &other)
+                .is_some()
+            {
+                found.push(Overlap {
+                    entity: *entity,
+                    collider: id.clone(),
+                });
+            }
+        }
+        found
+    }
+
+    /// First collider hit by `shape`, moved from `transform` along the unit `direction`.
+    /// The shape is advanced toward each collider by the gap between them, so it cannot skip
+    /// over a collider however thin, see `time_of_impact`
+    pub(crate) fn shape_cast(
+        &self,
+        components: &ComponentStore,
+        shape: &ColliderShape,
+        transform: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RayHit> {
+        self.cast_shape(
+            components,
+            shape,
+            transform,
+            direction,
+            max_distance,
+            |collider, entity| filter.accepts(collider, entity),
+            false,
+        )
+    }
+
+    /// Like `shape_cast`, but the colliders the shape overlaps from the start aren't hit
+    pub(crate) fn shape_cast_from_outside(
+        &self,
+        components: &ComponentStore,
+        shape: &ColliderShape,
+        transform: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RayHit> {
+        self.cast_shape(
+            components,
+            shape,
+            transform,
+            direction,
+            max_distance,
+            |collider, entity| filter.accepts(collider, entity),
+            true,
+        )
+    }
+
+    /// First collider hit by the shape moving from `transform` along the unit `direction`,
+    /// among those `accepts`. Colliders the shape overlaps from the start are hit at a distance
+    /// of 0, unless `skip_overlapping`
+    #[allow(clippy::too_many_arguments)]
+    fn cast_shape(
+        &self,
+        components: &ComponentStore,
+        shape: &ColliderShape,
+        transform: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        accepts: impl Fn(&Collider, &EntityId) -> bool,
+        skip_overlapping: bool,
+    ) -> Option<RayHit> {
+        let moved = |distance: f32| Transform::from_translation(direction * distance) * *transform;
+        // only colliders within the bounds of the whole cast can be hit
+        let swept = shape
+            .world_bounds(transform)
+            .union(&shape.world_bounds(&moved(max_distance)));
+        let pieces = convex_pieces(shape, transform, &swept);
+
+        let mut best: Option<RayHit> = None;
+        for ColliderInfo(collider, entity, other) in self.candidates(components, &swept) {
+            let Some(collider) = enabled_collider(components, collider) else {
+                continue;
+            };
+            if !accepts(collider, entity) {
+                continue;
+            }
+            let Some(other) = components.get_ref::<TransformComponent>(other) else {
+                continue;
+            };
+            let other = other.global();
+            // meshes don't collide with each other
+            if matches!(
+                (shape, collider.shape()),
+                (ColliderShape::Mesh(_), ColliderShape::Mesh(_))
+            ) {
+                continue;
+            }
+            let overlap = |distance: f32| {
+                ColliderShape::get_correction_vec(shape, &moved(distance), collider.shape(), &other)
+            };
+            if skip_overlapping && overlap(0.).is_some() {
+                continue;
+            }
+            // only look for hits closer than the best one so far
+            let limit = best.as_ref().map_or(max_distance, |b| b.distance);
+
+            let targets = convex_pieces(collider.shape(), &other, &swept);
+            let Some((distance, normal)) = pieces
+                .iter()
+                .flat_map(|piece| {
+                    targets
+                        .iter()
+                        .filter_map(|target| time_of_impact(piece, target, direction, limit))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+            else {
+                continue;
+            };
 
-                let (a_trans, b_trans) = components
-                    .get_mut_disjoint_2::<TransformComponent, TransformComponent>([
-                        &a_trans, &b_trans,
-                    ]);
-                let a_trans = a_trans.unwrap();
-                let b_trans = b_trans.unwrap();
-
-                let vec = Collider::get_correction_vec(
-                    &col_a,
-                    &a_trans.global(),
-                    &col_b,
-                    &b_trans.global(),
+            // overlapping from the start: the correction moves the shape back out of the
+            // collider, along its normal
+            let normal = normal
+                .or_else(|| {
+                    overlap(distance)
+                        .filter(|c| c.magnitude2() > 0.)
+                        .map(|c| c.normalize())
+                })
+                .unwrap_or(-direction);
+            // the point of the collider closest to the shape, under its origin
+            let origin = moved(distance).translation();
+            let point = collider
+                .shape()
+                .ray_intersection(&other, &Ray::new(origin, -normal))
+                .map_or(origin, |(distance, _)| origin - normal * distance);
+            best = Some(RayHit {
+                entity: *entity,
+                distance,
+                point,
+                normal,
+            });
+        }
+        best
+    }
+
+    /// Colliders whose bounds may overlap `bounds`, looked up in the hierarchies built by the
+    /// last pass. The colliders of entities moved since then are checked with their current
+    /// bounds instead, and so are all of the colliders of lists changed since then
+    fn candidates(&self, components: &ComponentStore, bounds: &Bounds) -> Vec<&ColliderInfo> {
+        let lists = self.lists();
+        let overlapping = |info: &&ColliderInfo| {
+            world_bounds(info, components).is_some_and(|other| other.overlaps(bounds))
+        };
+        let not_moved = |info: &&ColliderInfo| !self.moved.contains(&info.1);
+
+        let mut found = vec![];
+        // lists without a hierarchy
+        let mut searched = vec![];
+        match &self.static_bvh {
+            Some(bvh) => found.extend(
+                bvh.query(bounds)
+                    .into_iter()
+                    .map(|idx| &self.static_colliders[idx])
+                    .filter(not_moved),
+            ),
+            None => searched.push(0),
+        }
+        match &self.moving_bvh {
+            Some(bvh) => {
+                let triggers_from = self.dynamic_colliders.len();
+                found.extend(
+                    bvh.query(bounds)
+                        .into_iter()
+                        .map(|idx| match idx.checked_sub(triggers_from) {
+                            Some(trigger) => &self.trigger_colliders[trigger],
+                            None => &self.dynamic_colliders[idx],
+                        })
+                        .filter(not_moved),
                 );
+            }
+            None => searched.extend([1, 2]),
+        }
 
-                match vec {
-                    Some(vec) => {
-                        // only a is dynamic
-                        a_trans.translate_global(vec);
-                    }
-                    // no collision
-                    None => continue,
+        for entity in &self.moved {
+            let colliders = self.entity_colliders.get(entity).into_iter().flatten();
+            for (list, idx) in colliders {
+                if !searched.contains(list) {
+                    found.extend(Some(&lists[*list][*idx]).filter(overlapping));
                 }
             }
         }
+        for list in searched {
+            found.extend(lists[list].iter().filter(overlapping));
+        }
+        found
     }
 
+    /// Remove all colliders belonging to an entity
+    pub(crate) fn remove_entity(&mut self, entity: &EntityId) {
+        self.static_colliders.retain(|c| c.1 != *entity);
+        self.dynamic_colliders.retain(|c| c.1 != *entity);
+        self.trigger_colliders.retain(|c| c.1 != *entity);
+        self.static_bvh = None;
+        self.moving_bvh = None;
+    }
+
+    /// The lists of static, dynamic and trigger colliders
+    fn lists(&self) -> [&Vec<ColliderInfo>; 3] {
+        [
+            &self.static_colliders,
+            &self.dynamic_colliders,
+            &self.trigger_colliders,
+        ]
+    }
+
+    /// The lists of static, dynamic and trigger colliders
+    fn lists_mut(&mut self) -> [&mut Vec<ColliderInfo>; 3] {
+        [
+            &mut self.static_colliders,
+            &mut self.dynamic_colliders,
+            &mut self.trigger_colliders,
+        ]
+    }
+
+    /// Register a collider component added to `entity`, moved by the `transform` component
     pub(crate) fn add_collider(
         &mut self,
+        id: ComponentId,
+        collider: &Collider,
         entity: EntityId,
-        collider: Collider,
         transform: ComponentId,
     ) {
-        if collider.dynamic() {
-            self.dynamic_colliders
-                .push(ColliderInfo(collider, entity, transform))
+        let list = list_index(collider);
+        self.lists_mut()[list].push(ColliderInfo(id, entity, transform));
+        self.invalidate(list);
+    }
+
+    /// Unregister a removed collider component, returning its entity and transform
+    pub(crate) fn remove_collider(&mut self, id: &ComponentId) -> Option<(EntityId, ComponentId)> {
+        let (list, idx) = self.find(id)?;
+        let ColliderInfo(_, entity, transform) = self.lists_mut()[list].remove(idx);
+        self.invalidate(list);
+        Some((entity, transform))
+    }
+
+    /// Drop the hierarchy of a list whose colliders changed, until the next pass
+    fn invalidate(&mut self, list: usize) {
+        if list == 0 {
+            self.static_bvh = None;
         } else {
-            self.static_colliders
-                .push(ColliderInfo(collider, entity, transform))
+            self.moving_bvh = None;
+        }
+    }
+
+    /// The list of the collider, and its index in it
+    fn find(&self, id: &ComponentId) -> Option<(usize, usize)> {
+        self.lists()
+            .iter()
+            .enumerate()
+            .find_map(|(list, colliders)| Some((list, colliders.iter().position(|c| c.0 == *id)?)))
+    }
+
+    /// Record that a collider component may have been mutated. Its shape and flags are read
+    /// from the component at once, but a collider made dynamic, static or a trigger only
+    /// changes of list at the next pass
+    pub(crate) fn mark_changed(&mut self, id: ComponentId) {
+        self.changed.insert(id);
+    }
+
+    /// Record that an entity may have moved, or its colliders changed, so that queries don't
+    /// rely on the bounds the last pass saw
+    pub(crate) fn mark_moved(&mut self, entity: EntityId) {
+        self.moved.insert(entity);
+    }
+
+    /// Move the changed colliders to the list of what they are now
+    fn sort_changed(&mut self, components: &ComponentStore) {
+        for id in std::mem::take(&mut self.changed) {
+            let (Some(collider), Some((list, _))) =
+                (components.get_ref::<Collider>(&id), self.find(&id))
+            else {
+                continue;
+            };
+            if list != list_index(collider) {
+                let (entity, transform) = self.remove_collider(&id).unwrap();
+                self.add_collider(id, collider, entity, transform);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use crate::{
+        entity::EntityId, transform::Transform, Collider, ColliderShape, CollisionPhase,
+        QueryFilter, RigidBody, Scene, Sphere, Vector3,
+    };
+
+    fn place(scene: &mut Scene, entity: &EntityId, position: Vector3) {
+        scene
+            .get_mut_transform(entity)
+            .unwrap()
+            .set_position_global(position);
+    }
+
+    fn position(scene: &mut Scene, entity: &EntityId) -> Vector3 {
+        scene.get_mut_transform(entity).unwrap().position_global()
+    }
+
+    #[test]
+    fn test_static_hierarchy_follows_moves() {
+        let mut scene = Scene::new();
+        let floor = scene
+            .add_entity(scene.get_root(), "floor".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                floor,
+                Collider::new_aabb(vec3(0., -1., 0.), vec3(5., 1., 5.), false),
+            )
+            .unwrap();
+
+        // a row of boxes, only the first two overlapping
+        let boxes = (0..4)
+            .map(|i| {
+                let entity = scene
+                    .add_entity(scene.get_root(), format!("box {i}"))
+                    .unwrap();
+                let x = if i == 0 { 0. } else { i as f32 * 3. - 2.5 };
+                place(&mut scene, &entity, vec3(x, 1., 0.));
+                scene
+                    .add_collider(
+                        entity,
+                        Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 0.5, 0.5), true),
+                    )
+                    .unwrap();
+                entity
+            })
+            .collect::<Vec<_>>();
+        let ball = scene
+            .add_entity(scene.get_root(), "ball".to_string())
+            .unwrap();
+        place(&mut scene, &ball, vec3(0., 0.4, 3.));
+        scene
+            .add_collider(ball, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        approx::assert_abs_diff_eq!(position(&mut scene, &ball).y, 0.5, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(position(&mut scene, &boxes[0]).x, -0.25, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(position(&mut scene, &boxes[1]).x, 0.75, epsilon = 1e-5);
+        assert_eq!(position(&mut scene, &boxes[2]), vec3(3.5, 1., 0.));
+
+        // the floor moves away: the ball sinks freely
+        place(&mut scene, &floor, vec3(20., 0., 0.));
+        place(&mut scene, &ball, vec3(0., 0.4, 3.));
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        assert_eq!(position(&mut scene, &ball), vec3(0., 0.4, 3.));
+
+        // and it's found again where it went
+        place(&mut scene, &ball, vec3(20., 0.4, 3.));
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        approx::assert_abs_diff_eq!(position(&mut scene, &ball).y, 0.5, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_continuous_colliders_stop_at_walls() {
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., 0., 0.));
+        let wall = scene
+            .add_entity(scene.get_root(), "wall".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                wall,
+                Collider::new_aabb(vec3(5., 0., 0.), vec3(0.05, 2., 2.), false),
+            )
+            .unwrap();
+
+        let bullets = [false, true].map(|continuous| {
+            let bullet = scene
+                .add_entity(scene.get_root(), "bullet".to_string())
+                .unwrap();
+            let collider =
+                Collider::new_sphere(vec3(0., 0., 0.), 0.1, true).with_continuous(continuous);
+            scene.add_collider(bullet, collider).unwrap();
+            let body = scene
+                .add_component(bullet, RigidBody::new_kinematic(vec3(200., 0., 0.)))
+                .unwrap();
+            (bullet, body)
+        });
+        // the first update only remembers where the bullets start from
+        for _ in 0..2 {
+            scene.on_update(Duration::from_millis(16)).unwrap();
+        }
+
+        // 3.2 per update: the wall is skipped over without sweeping
+        let (bullet, _) = &bullets[0];
+        approx::assert_abs_diff_eq!(position(&mut scene, bullet).x, 6.4, epsilon = 1e-4);
+        let (bullet, _) = &bullets[1];
+        approx::assert_abs_diff_eq!(position(&mut scene, bullet).x, 4.85, epsilon = 1e-3);
+        let hit = scene
+            .collision_events()
+            .iter()
+            .find(|(entity, e)| entity == bullet && e.other == wall)
+            .map(|(_, e)| e.clone())
+            .unwrap();
+        assert_eq!(hit.phase, CollisionPhase::Enter);
+        approx::assert_abs_diff_eq!(hit.normal.x, -1., epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_sweeps_hit_thin_walls_far_away() {
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., 0., 0.));
+        let wall = scene
+            .add_entity(scene.get_root(), "wall".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                wall,
+                Collider::new_aabb(vec3(70., 0., 0.), vec3(0.005, 2., 2.), false),
+            )
+            .unwrap();
+        let bullet = scene
+            .add_entity(scene.get_root(), "bullet".to_string())
+            .unwrap();
+        let collider = Collider::new_sphere(vec3(0., 0., 0.), 0.01, true).with_continuous(true);
+        scene.add_collider(bullet, collider).unwrap();
+        scene
+            .add_component(bullet, RigidBody::new_kinematic(vec3(3000., 0., 0.)))
+            .unwrap();
+
+        // 48 per update: over 1024 times the thickness of the bullet and the wall together.
+        // The first update only remembers where the bullet starts from
+        for _ in 0..2 {
+            scene.on_update(Duration::from_millis(16)).unwrap();
+        }
+        approx::assert_abs_diff_eq!(position(&mut scene, &bullet).x, 69.985, epsilon = 1e-3);
+
+        let shape = ColliderShape::Sphere(Sphere::new(vec3(0., 0., 0.), 0.01));
+        let start = Transform::from_translation(vec3(0., 1., 0.));
+        let hit = scene
+            .shape_cast(
+                &shape,
+                &start,
+                Vector3::unit_x(),
+                1000.,
+                &QueryFilter::new(),
+            )
+            .unwrap();
+        assert_eq!(hit.entity, wall);
+        approx::assert_abs_diff_eq!(hit.distance, 69.985, epsilon = 1e-3);
+    }
+}
@@ -0,0 +1,225 @@
+use cgmath::vec3;
+
+use crate::Vector3;
+
+/// Box aligned with the world axis around a collider, used to find the pairs of colliders
+/// that may overlap before testing their exact shapes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Bounds {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Bounds {
+    pub fn new(min: Vector3, max: Vector3) -> Self {
+        Self { min, max }
+    }
+
+    /// Bounds around all of the points
+    pub fn around(points: impl IntoIterator<Item = Vector3>) -> Self {
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = -min;
+        for p in points {
+            min = vec3(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = vec3(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        Self { min, max }
+    }
+
+    /// Touching bounds overlap, the exact shapes decide if they collide
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Self::around([self.min, self.max, other.min, other.max])
+    }
+
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) / 2.
+    }
+}
+
+/// Pairs of items whose bounds overlap, found by sorting the bounds along x and sweeping over
+/// them: only items whose x intervals overlap are compared. Pairs are `(smaller, larger)` item,
+/// sorted
+pub(crate) fn sweep_and_prune(items: &[(usize, Bounds)]) -> Vec<(usize, usize)> {
+    let mut sorted = items.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.1.min.x.total_cmp(&b.1.min.x));
+
+    let mut pairs = vec![];
+    let mut active: Vec<&(usize, Bounds)> = vec![];
+    for item in sorted {
+        // items ending before this one starts can't overlap anything left
+        active.retain(|other| other.1.max.x >= item.1.min.x);
+        for other in &active {
+            if other.1.overlaps(&item.1) {
+                pairs.push((other.0.min(item.0), other.0.max(item.0)));
+            }
+        }
+        active.push(item);
+    }
+    pairs.sort();
+    pairs
+}
+
+#[derive(Debug, Clone)]
+enum BvhNode {
+    Leaf {
+        bounds: Bounds,
+        item: usize,
+    },
+    Branch {
+        bounds: Bounds,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &Bounds {
+        match self {
+            BvhNode::Leaf { bounds, .. } | BvhNode::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy: a binary tree of bounds, each node around its children.
+/// Queries skip the subtrees whose bounds miss, so the tree is worth building for items that
+/// rarely move
+#[derive(Debug, Clone)]
+pub(crate) struct Bvh {
+    nodes: Vec<BvhNode>,
+    root: Option<usize>,
+}
+
+impl Bvh {
+    pub fn new(items: Vec<(usize, Bounds)>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(items.len() * 2),
+            root: None,
+        };
+        if !items.is_empty() {
+            bvh.root = Some(bvh.build(items));
+        }
+        bvh
+    }
+
+    /// Split the items in two halves along the longest axis of their centers, recursively
+    fn build(&mut self, mut items: Vec<(usize, Bounds)>) -> usize {
+        if let [(item, bounds)] = items[..] {
+            self.nodes.push(BvhNode::Leaf { bounds, item });
+            return self.nodes.len() - 1;
+        }
+
+        let centers = Bounds::around(items.iter().map(|(_, b)| b.center()));
+        let extent = centers.max - centers.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        items.sort_by(|a, b| a.1.center()[axis].total_cmp(&b.1.center()[axis]));
+        let right = items.split_off(items.len() / 2);
+
+        let left = self.build(items);
+        let right = self.build(right);
+        let bounds = self.nodes[left].bounds().union(self.nodes[right].bounds());
+        self.nodes.push(BvhNode::Branch {
+            bounds,
+            left,
+            right,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Bounds around all of the items, None if there are none
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.root.map(|root| *self.nodes[root].bounds())
+    }
+
+    /// Items whose bounds overlap `bounds`
+    pub fn query(&self, bounds: &Bounds) -> Vec<usize> {
+        self.query_with(|node| node.overlaps(bounds))
+    }
+
+    /// Items in the leaves reached by going down the nodes whose bounds pass `test`
+    pub fn query_with(&self, test: impl Fn(&Bounds) -> bool) -> Vec<usize> {
+        let mut found = vec![];
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            if !test(node.bounds()) {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf { item, .. } => found.push(*item),
+                BvhNode::Branch { left, right, .. } => stack.extend([*left, *right]),
+            }
+        }
+        found.sort();
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> Vec<(usize, Bounds)> {
+        // unit boxes every 1.5 along x and y, a bigger one across some of them
+        let mut items = vec![];
+        for i in 0..6 {
+            for j in 0..6 {
+                let min = vec3(i as f32 * 1.5, j as f32 * 1.5, 0.);
+                items.push((items.len(), Bounds::new(min, min + vec3(1., 1., 1.))));
+            }
+        }
+        items.push((
+            items.len(),
+            Bounds::new(vec3(2., 2., 0.5), vec3(5., 4., 2.)),
+        ));
+        items
+    }
+
+    #[test]
+    fn test_sweep_and_prune_matches_brute_force() {
+        let items = grid();
+        let mut expected = vec![];
+        for (i, a) in &items {
+            for (j, b) in &items {
+                if i < j && a.overlaps(b) {
+                    expected.push((*i, *j));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(sweep_and_prune(&items), expected);
+    }
+
+    #[test]
+    fn test_bvh_query_matches_brute_force() {
+        let items = grid();
+        let bvh = Bvh::new(items.clone());
+        for query in [
+            Bounds::new(vec3(0., 0., 0.), vec3(0.5, 0.5, 0.5)),
+            Bounds::new(vec3(2.8, 2.8, 0.), vec3(4.6, 3.2, 0.2)),
+            Bounds::new(vec3(100., 0., 0.), vec3(101., 1., 1.)),
+        ] {
+            let expected = items
+                .iter()
+                .filter(|(_, b)| b.overlaps(&query))
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>();
+            assert_eq!(bvh.query(&query), expected);
+        }
+        assert!(Bvh::new(vec![]).query(&items[0].1).is_empty());
+    }
+}
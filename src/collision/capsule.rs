@@ -0,0 +1,265 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{
+        aabb::AxisAlignedBoundingBox,
+        query::Ray,
+        sphere::{max_scale, ray_sphere, sphere_box_correction, spheres_correction, Sphere},
+    },
+    transform::Transform,
+    Vector3,
+};
+
+/// Segment from `start` to `end` grown by a radius, relative to the entity: a pill shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capsule {
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub start: Vector3,
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub end: Vector3,
+    pub radius: f32,
+}
+
+impl Capsule {
+    pub fn new(start: Vector3, end: Vector3, radius: f32) -> Self {
+        Self { start, end, radius }
+    }
+
+    /// Ends of the segment and radius in the world. The radius grows with the largest scale of
+    /// the transform
+    pub(crate) fn world(&self, transform: &Transform) -> (Vector3, Vector3, f32) {
+        (
+            *transform * self.start,
+            *transform * self.end,
+            self.radius * max_scale(transform),
+        )
+    }
+
+    /// Distance along the ray to the capsule and normal where it's hit, None if it's missed
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        let (start, end, radius) = self.world(transform);
+        ray_capsule(ray.origin, ray.direction, start, end, radius)
+    }
+
+    /// Get the collision correction vector for this capsule if the other shape is also a capsule
+    pub fn capsule_correction_vec(
+        a: &Capsule,
+        a_transform: &Transform,
+        b: &Capsule,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (a_start, a_end, a_radius) = a.world(a_transform);
+        let (b_start, b_end, b_radius) = b.world(b_transform);
+        let (a_closest, b_closest) = closest_points_segments(a_start, a_end, b_start, b_end);
+        spheres_correction(a_closest, a_radius, b_closest, b_radius)
+    }
+
+    /// Get the collision correction vector for this capsule if the other shape is a sphere
+    pub fn sphere_correction_vec(
+        a: &Capsule,
+        a_transform: &Transform,
+        b: &Sphere,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (start, end, radius) = a.world(a_transform);
+        let (center, sphere_radius) = b.world(b_transform);
+        let closest = closest_point_segment(start, end, center);
+        spheres_correction(closest, radius, center, sphere_radius)
+    }
+
+    /// Get the collision correction vector for this capsule if the other shape is an AABB
+    pub fn aabb_correction_vec(
+        a: &Capsule,
+        a_transform: &Transform,
+        b: &AxisAlignedBoundingBox,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (start, end, radius) = a.world(a_transform);
+        let (min, max) = b.world_bounds(b_transform);
+        capsule_box_correction(start, end, radius, min, max)
+    }
+}
+
+/// Distance along the ray, of unit `direction`, to the capsule and normal where it's hit.
+/// The capsule is the union of a cylinder and two spheres, so the first of their hits is kept
+fn ray_capsule(
+    origin: Vector3,
+    direction: Vector3,
+    start: Vector3,
+    end: Vector3,
+    radius: f32,
+) -> Option<(f32, Vector3)> {
+    let closest = closest_point_segment(start, end, origin);
+    if (origin - closest).magnitude2() <= radius * radius {
+        return Some((0., -direction));
+    }
+
+    let mut hits = vec![
+        ray_sphere(origin, direction, start, radius),
+        ray_sphere(origin, direction, end, radius),
+    ];
+    let axis = end - start;
+    let length2 = axis.magnitude2();
+    if length2 > f32::EPSILON {
+        // the cylinder, ignoring the motion along its axis
+        let offset = origin - start;
+        let direction_perp = direction - axis * (direction.dot(axis) / length2);
+        let offset_perp = offset - axis * (offset.dot(axis) / length2);
+        let a = direction_perp.magnitude2();
+        let b = offset_perp.dot(direction_perp);
+        let c = offset_perp.magnitude2() - radius * radius;
+        let discriminant = b * b - a * c;
+        if a > f32::EPSILON && discriminant >= 0. {
+            let distance = (-b - discriminant.sqrt()) / a;
+            let point = origin + direction * distance;
+            let along = (point - start).dot(axis) / length2;
+            if distance >= 0. && (0. ..=1.).contains(&along) {
+                let normal = (point - (start + axis * along)).normalize();
+                hits.push(Some((distance, normal)));
+            }
+        }
+    }
+    hits.into_iter()
+        .flatten()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Vector moving the capsule out of the box, None if they don't overlap
+pub(crate) fn capsule_box_correction(
+    start: Vector3,
+    end: Vector3,
+    radius: f32,
+    min: Vector3,
+    max: Vector3,
+) -> Option<Vector3> {
+    // the signed distance to a box is convex, and so is its restriction to the segment:
+    // a ternary search finds the point of the segment deepest in (or closest to) the box
+    let distance = |t: f32| box_signed_distance(start + (end - start) * t, min, max);
+    let (mut low, mut high) = (0f32, 1f32);
+    for _ in 0..32 {
+        let left = low + (high - low) / 3.;
+        let right = high - (high - low) / 3.;
+        if distance(left) <= distance(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+    let deepest = start + (end - start) * ((low + high) / 2.);
+    sphere_box_correction(deepest, radius, min, max)
+}
+
+/// Point of the segment closest to `point`
+pub(crate) fn closest_point_segment(start: Vector3, end: Vector3, point: Vector3) -> Vector3 {
+    let direction = end - start;
+    let length2 = direction.magnitude2();
+    if length2 <= f32::EPSILON {
+        return start;
+    }
+    let t = ((point - start).dot(direction) / length2).clamp(0., 1.);
+    start + direction * t
+}
+
+/// Closest points between the segments p1-q1 and p2-q2 (from Real-Time Collision Detection)
+pub(crate) fn closest_points_segments(
+    p1: Vector3,
+    q1: Vector3,
+    p2: Vector3,
+    q2: Vector3,
+) -> (Vector3, Vector3) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.magnitude2();
+    let e = d2.magnitude2();
+    let f = d2.dot(r);
+
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        // both segments are points
+        (0., 0.)
+    } else if a <= f32::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            // parallel segments: any s works
+            let s = if denom > f32::EPSILON {
+                ((b * f - c * e) / denom).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let t = (b * s + f) / e;
+            if t < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if t > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Distance from the point to the box, negative inside of it
+fn box_signed_distance(point: Vector3, min: Vector3, max: Vector3) -> f32 {
+    let center = (min + max) / 2.;
+    let half = (max - min) / 2.;
+    let offset = point - center;
+    let q = vec3(
+        offset.x.abs() - half.x,
+        offset.y.abs() - half.y,
+        offset.z.abs() - half.z,
+    );
+    let outside = vec3(q.x.max(0.), q.y.max(0.), q.z.max(0.)).magnitude();
+    let inside = q.x.max(q.y).max(q.z).min(0.);
+    outside + inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capsule_correction_vec() {
+        // crossing capsules, one above the other
+        let a = Capsule::new(vec3(-1., 0., 0.), vec3(1., 0., 0.), 0.5);
+        let b = Capsule::new(vec3(0., 0., -1.), vec3(0., 0., 1.), 0.5);
+        let a_transform = Transform::from_translation(vec3(0., 0.75, 0.));
+        let b_transform = Transform::identity();
+
+        let vec = Capsule::capsule_correction_vec(&a, &a_transform, &b, &b_transform).unwrap();
+        approx::assert_abs_diff_eq!(vec.y, 0.25, epsilon = 1e-5);
+
+        let sphere = Sphere::new(vec3(0., 0., 0.), 0.5);
+        let sphere_transform = Transform::from_translation(vec3(0.9, 0.6, 0.));
+        let vec =
+            Capsule::sphere_correction_vec(&a, &b_transform, &sphere, &sphere_transform).unwrap();
+        approx::assert_abs_diff_eq!(vec.y, -0.4, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_capsule_aabb_correction_vec() {
+        // standing capsule, its bottom sunk into the floor
+        let capsule = Capsule::new(vec3(0., 0.5, 0.), vec3(0., 1.5, 0.), 0.5);
+        let floor = AxisAlignedBoundingBox::new(vec3(-5., -1., -5.), vec3(5., 0., 5.));
+        let transform = Transform::from_translation(vec3(0., -0.1, 0.));
+
+        let vec =
+            Capsule::aabb_correction_vec(&capsule, &transform, &floor, &Transform::identity())
+                .unwrap();
+        approx::assert_abs_diff_eq!(vec.y, 0.1, epsilon = 1e-3);
+        approx::assert_abs_diff_eq!(vec.x, 0., epsilon = 1e-3);
+
+        let above = Transform::from_translation(vec3(0., 0.1, 0.));
+        assert!(
+            Capsule::aabb_correction_vec(&capsule, &above, &floor, &Transform::identity())
+                .is_none()
+        );
+    }
+}
@@ -1,15 +1,119 @@
-use crate::{collision::aabb::AxisAlignedBoundingBox, transform::Transform, Component, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{
+        aabb::AxisAlignedBoundingBox, capsule::Capsule, mesh::TriangleMesh,
+        obb::OrientedBoundingBox, sphere::Sphere,
+    },
+    error::*,
+    transform::Transform,
+    CollisionLayers, Component, Vector3,
+};
 
 use super::shape::ColliderShape;
 
+/// A component giving its entity a shape to collide with. Like other components, colliders
+/// can be found, mutated and removed after they were added: the scene keeps the collision
+/// arena in sync with them
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Collider {
     shape: ColliderShape,
     dynamic: bool, // false -> collider is static
+    /// Triggers detect overlaps without pushing anything
+    #[serde(default)]
+    trigger: bool,
+    /// Layer from 0 to 31
+    #[serde(default)]
+    layer: u8,
+    /// Layers the collider interacts with, as a bit mask
+    #[serde(default = "all_layers")]
+    mask: u32,
+    /// Swept from its last position, so that it can't pass through thin colliders
+    #[serde(default)]
+    continuous: bool,
+    /// Disabled colliders neither collide, nor are hit by queries
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn all_layers() -> u32 {
+    u32::MAX
+}
+
+fn enabled() -> bool {
+    true
 }
 
 impl Collider {
     pub fn new(shape: ColliderShape, dynamic: bool) -> Self {
-        Self { shape, dynamic }
+        Self {
+            shape,
+            dynamic,
+            trigger: false,
+            layer: 0,
+            mask: all_layers(),
+            continuous: false,
+            enabled: true,
+        }
+    }
+
+    /// Make the collider a trigger: it reports other colliders entering and leaving it,
+    /// but neither pushes them nor is pushed
+    pub fn with_trigger(mut self, trigger: bool) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    pub fn is_trigger(&self) -> bool {
+        self.trigger
+    }
+
+    /// Put the collider on a layer, from 0 to 31. Colliders are on layer 0 by default
+    pub fn with_layer(mut self, layer: u8) -> Result<Self> {
+        if layer as usize >= CollisionLayers::MAX_LAYERS {
+            return Err(Error::InvalidLayer(layer));
+        }
+        self.layer = layer;
+        Ok(self)
+    }
+
+    pub fn layer(&self) -> u8 {
+        self.layer
+    }
+
+    /// Only interact with colliders on the layers of the mask, see `CollisionLayers::mask`.
+    /// Colliders interact with all layers by default
+    pub fn with_mask(mut self, mask: u32) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// Enable continuous collision detection for a dynamic collider: each pass casts it from
+    /// where the last pass left it to where it is, and stops it at the first collider hit,
+    /// instead of only checking where it ends up. Fast and small colliders need it not to
+    /// pass through thin ones. Teleporting such a collider sweeps it too
+    pub fn with_continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+
+    pub fn is_continuous(&self) -> bool {
+        self.continuous
+    }
+
+    /// The layer as a bit of a layer mask
+    pub(crate) fn layer_bit(&self) -> u32 {
+        1 << self.layer
+    }
+
+    /// Two colliders collide, or a trigger detects a collider, only if each one's mask has
+    /// the other's layer
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.mask & other.layer_bit() != 0 && other.mask & self.layer_bit() != 0
     }
 
     pub fn contains(
@@ -34,14 +138,61 @@ impl Collider {
         self.dynamic
     }
 
+    /// Make the collider dynamic, or static. The change is picked up by the next collider pass
+    pub fn set_dynamic(&mut self, dynamic: bool) {
+        self.dynamic = dynamic;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disable the collider, or enable it again: disabled colliders are ignored by the
+    /// collider pass and by queries, as if they had been removed
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
     /// Construct a new AABB shaped collider: (axis-aligned bounding box)
     pub fn new_aabb(position: Vector3, dimensions: Vector3, dynamic: bool) -> Self {
         let min = position - dimensions;
         let max = position + dimensions;
-        Self {
-            shape: ColliderShape::AABB(AxisAlignedBoundingBox::new(min, max)),
+        Self::new(
+            ColliderShape::AABB(AxisAlignedBoundingBox::new(min, max)),
             dynamic,
-        }
+        )
+    }
+
+    /// Construct a new OBB shaped collider: (oriented bounding box), which rotates with the
+    /// entity
+    pub fn new_obb(position: Vector3, dimensions: Vector3, dynamic: bool) -> Self {
+        Self::new(
+            ColliderShape::OBB(OrientedBoundingBox::new(position, dimensions)),
+            dynamic,
+        )
+    }
+
+    /// Construct a new sphere shaped collider, around `center`
+    pub fn new_sphere(center: Vector3, radius: f32, dynamic: bool) -> Self {
+        Self::new(ColliderShape::Sphere(Sphere::new(center, radius)), dynamic)
+    }
+
+    /// Construct a new capsule shaped collider: the segment from `start` to `end`, grown by
+    /// `radius`
+    pub fn new_capsule(start: Vector3, end: Vector3, radius: f32, dynamic: bool) -> Self {
+        Self::new(
+            ColliderShape::Capsule(Capsule::new(start, end, radius)),
+            dynamic,
+        )
+    }
+
+    /// Construct a new static collider from the triangles of a mesh, see `TriangleMesh`
+    pub fn new_mesh(mesh: TriangleMesh) -> Self {
+        Self::new(ColliderShape::Mesh(mesh), false)
+    }
+
+    pub fn shape(&self) -> &ColliderShape {
+        &self.shape
     }
 }
 
@@ -52,3 +203,92 @@ impl Component for Collider {
 
     fn on_event(&mut self, scene: &mut crate::Scene, context: crate::OnEventContext) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{CollisionPhase, QueryFilter, Ray, Scene};
+
+    #[test]
+    fn test_colliders_follow_component_changes() {
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., 0., 0.));
+        let wall = scene
+            .add_entity(scene.get_root(), "wall".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                wall,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(1., 1., 1.), false),
+            )
+            .unwrap();
+        let block = scene
+            .add_entity(scene.get_root(), "block".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&block)
+            .unwrap()
+            .set_position_global(vec3(1.5, 0., 0.));
+        let collider = scene
+            .add_component(
+                block,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(1., 1., 1.), false),
+            )
+            .unwrap();
+        assert_eq!(
+            scene.get_first_component_id_from_entity::<Collider>(&block),
+            Some(collider.clone())
+        );
+
+        // two statics overlap without moving
+        scene.on_update(Duration::ZERO).unwrap();
+        assert!(scene.collision_events().is_empty());
+        let position = scene.get_mut_transform(&block).unwrap().position_global();
+        assert_eq!(position, vec3(1.5, 0., 0.));
+
+        // made dynamic, the block is pushed out of the wall
+        scene
+            .get_mut_component::<Collider>(&collider)
+            .unwrap()
+            .set_dynamic(true);
+        scene.on_update(Duration::ZERO).unwrap();
+        let position = scene.get_mut_transform(&block).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.x, 2., epsilon = 1e-5);
+        assert_eq!(scene.collision_events()[0].1.phase, CollisionPhase::Enter);
+
+        // grown, then disabled: the collisions end and rays go through
+        let ray = Ray::new(vec3(5., 0., 0.), vec3(-1., 0., 0.));
+        *scene.get_mut_component::<Collider>(&collider).unwrap() =
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(1.5, 1., 1.), true);
+        let hit = scene.raycast(&ray, 10., &QueryFilter::new()).unwrap();
+        approx::assert_abs_diff_eq!(hit.distance, 1.5, epsilon = 1e-5);
+        scene
+            .get_mut_component::<Collider>(&collider)
+            .unwrap()
+            .set_enabled(false);
+        scene.on_update(Duration::ZERO).unwrap();
+        assert_eq!(scene.collision_events()[0].1.phase, CollisionPhase::Exit);
+        let hit = scene.raycast(&ray, 10., &QueryFilter::new()).unwrap();
+        assert_eq!(hit.entity, wall);
+
+        // removed, the collider is gone for good
+        scene.remove_component(&collider).unwrap();
+        assert!(scene.get_ref_component::<Collider>(&collider).is_none());
+        assert!(scene.colliders_of(&block).is_empty());
+        assert!(matches!(
+            scene.remove_component(&collider),
+            Err(Error::SceneError(SceneError::ComponentNotFound(_)))
+        ));
+        let transform = scene.get_transform(&block).unwrap();
+        assert!(matches!(
+            scene.remove_component(&transform),
+            Err(Error::SceneError(SceneError::TransformRemoval(_)))
+        ));
+        scene.on_update(Duration::ZERO).unwrap();
+        scene.validate().unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use cgmath::InnerSpace;
+
+use crate::{entity::EntityId, Vector3};
+
+/// Where a collision is in its lifetime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPhase {
+    /// The entities started overlapping this update
+    Enter,
+    /// The entities were already overlapping during the last update
+    Stay,
+    /// The entities stopped overlapping this update
+    Exit,
+}
+
+/// A collision, as seen by one of the two entities involved
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionEvent {
+    pub other: EntityId,
+    /// Unit vector pointing away from the other entity: the direction this entity is pushed in
+    pub normal: Vector3,
+    /// How deep the colliders overlapped. For an exit, the normal and depth are those of the
+    /// last update the entities were touching
+    pub depth: f32,
+    pub phase: CollisionPhase,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    // from the second entity of the pair towards the first
+    normal: Vector3,
+    depth: f32,
+}
+
+/// Contacts between pairs of entities, tracked across updates to know the phase of collisions
+pub(crate) struct ContactTracker {
+    // pairs are ordered, so that each pair is only stored once
+    touching: HashMap<(EntityId, EntityId), Contact>,
+    current: HashMap<(EntityId, EntityId), Contact>,
+    events: Vec<(EntityId, CollisionEvent)>,
+}
+
+impl ContactTracker {
+    pub fn new() -> Self {
+        Self {
+            touching: HashMap::new(),
+            current: HashMap::new(),
+            events: vec![],
+        }
+    }
+
+    /// Record that `a` overlaps `b` during this pass, `correction` moving `a` out of `b`.
+    /// Entities overlapping through several colliders keep their deepest contact
+    pub fn record(&mut self, a: EntityId, b: EntityId, correction: Vector3) {
+        let depth = correction.magnitude();
+        if depth > f32::EPSILON {
+            self.record_touch(a, b, correction / depth, depth);
+        } else {
+            // too shallow to have a direction, it doesn't replace a touch that has one
+            self.record_touch(a, b, Vector3::unit_y(), 0.);
+        }
+    }
+
+    /// Record that `a` touches `b` during this pass, `normal` pushing `a` away from `b`
+    pub fn record_touch(&mut self, a: EntityId, b: EntityId, normal: Vector3, depth: f32) {
+        let (key, normal) = if a < b {
+            ((a, b), normal)
+        } else {
+            ((b, a), -normal)
+        };
+        let contact = Contact { normal, depth };
+        self.current
+            .entry(key)
+            .and_modify(|c| {
+                if depth > c.depth {
+                    *c = contact
+                }
+            })
+            .or_insert(contact);
+    }
+
+    /// Compare the contacts of this pass to the last one, creating the events of the pass
+    pub fn finish_pass(&mut self) {
+        self.events.clear();
+        let current = std::mem::take(&mut self.current);
+        // keep events in a stable order
+        let mut pairs = current.iter().collect::<Vec<_>>();
+        pairs.sort_by_key(|(pair, _)| **pair);
+        for (pair, contact) in pairs {
+            let phase = if self.touching.contains_key(pair) {
+                CollisionPhase::Stay
+            } else {
+                CollisionPhase::Enter
+            };
+            self.push_events(*pair, *contact, phase);
+        }
+        let mut ended = self
+            .touching
+            .iter()
+            .filter(|(pair, _)| !current.contains_key(pair))
+            .map(|(pair, contact)| (*pair, *contact))
+            .collect::<Vec<_>>();
+        ended.sort_by_key(|(pair, _)| *pair);
+        for (pair, contact) in ended {
+            self.push_events(pair, contact, CollisionPhase::Exit);
+        }
+        self.touching = current;
+    }
+
+    fn push_events(
+        &mut self,
+        (a, b): (EntityId, EntityId),
+        contact: Contact,
+        phase: CollisionPhase,
+    ) {
+        self.events.push((
+            a,
+            CollisionEvent {
+                other: b,
+                normal: contact.normal,
+                depth: contact.depth,
+                phase,
+            },
+        ));
+        self.events.push((
+            b,
+            CollisionEvent {
+                other: a,
+                normal: -contact.normal,
+                depth: contact.depth,
+                phase,
+            },
+        ));
+    }
+
+    /// Events of the last pass, with the entity receiving each of them
+    pub fn events(&self) -> &[(EntityId, CollisionEvent)] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{
+        event::{OnCollisionContext, OnEventContext, OnStartContext, OnUpdateContext},
+        Collider, Component, Scene,
+    };
+
+    struct Recorder(Arc<Mutex<Vec<(EntityId, CollisionEvent)>>>);
+
+    impl Component for Recorder {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn on_collision(&mut self, _scene: &mut Scene, context: OnCollisionContext) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((context.entity, context.collision));
+        }
+    }
+
+    #[test]
+    fn test_collision_phases() {
+        let mut scene = Scene::new();
+        let wall = scene
+            .add_entity(scene.get_root(), "wall".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                wall,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(1., 1., 1.), false),
+            )
+            .unwrap();
+
+        let ball = scene
+            .add_entity(scene.get_root(), "ball".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .set_position_global(vec3(1.25, 0., 0.));
+        scene
+            .add_collider(ball, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+
+        let received = Arc::new(Mutex::new(vec![]));
+        scene
+            .add_component(wall, Recorder(received.clone()))
+            .unwrap();
+        scene
+            .add_component(ball, Recorder(received.clone()))
+            .unwrap();
+
+        // the ball is pushed out of the wall, each entity hears about it
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 2);
+            let (_, on_ball) = received.iter().find(|(e, _)| *e == ball).unwrap();
+            assert_eq!(on_ball.other, wall);
+            assert_eq!(on_ball.phase, CollisionPhase::Enter);
+            approx::assert_abs_diff_eq!(on_ball.depth, 0.25, epsilon = 1e-5);
+            approx::assert_abs_diff_eq!(on_ball.normal.x, 1., epsilon = 1e-5);
+            let (_, on_wall) = received.iter().find(|(e, _)| *e == wall).unwrap();
+            assert_eq!(on_wall.other, ball);
+            approx::assert_abs_diff_eq!(on_wall.normal.x, -1., epsilon = 1e-5);
+        }
+        assert_eq!(scene.collision_events().len(), 2);
+
+        // pushed back in: still touching
+        received.lock().unwrap().clear();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .translate_global(vec3(-0.1, 0., 0.));
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        let phases = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, c)| c.phase)
+            .collect::<Vec<_>>();
+        assert_eq!(phases, vec![CollisionPhase::Stay, CollisionPhase::Stay]);
+
+        // moved away
+        received.lock().unwrap().clear();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .translate_global(vec3(2., 0., 0.));
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        let phases = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, c)| c.phase)
+            .collect::<Vec<_>>();
+        assert_eq!(phases, vec![CollisionPhase::Exit, CollisionPhase::Exit]);
+
+        // nothing left to report
+        received.lock().unwrap().clear();
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        assert!(received.lock().unwrap().is_empty());
+        assert!(scene.collision_events().is_empty());
+    }
+}
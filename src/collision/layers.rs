@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+
+/// Names of the 32 collision layers of a scene. Layer 0 is the "default" layer every collider
+/// starts on, the others are given out in order by `add`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CollisionLayers {
+    // the name of each layer, by index
+    names: Vec<String>,
+}
+
+impl CollisionLayers {
+    pub const MAX_LAYERS: usize = 32;
+
+    pub fn new() -> Self {
+        Self {
+            names: vec!["default".to_string()],
+        }
+    }
+
+    /// Name a new layer, returning its index. Naming an existing layer again returns it
+    pub fn add(&mut self, name: &str) -> Result<u8> {
+        if let Ok(layer) = self.layer(name) {
+            return Ok(layer);
+        }
+        if self.names.len() >= Self::MAX_LAYERS {
+            return Err(Error::Other(format!(
+                "all {} collision layers are taken, {name} can't be added",
+                Self::MAX_LAYERS
+            )));
+        }
+        self.names.push(name.to_string());
+        Ok((self.names.len() - 1) as u8)
+    }
+
+    pub fn layer(&self, name: &str) -> Result<u8> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|idx| idx as u8)
+            .ok_or(Error::LayerNotFound(name.to_string()))
+    }
+
+    pub fn name(&self, layer: u8) -> Option<&str> {
+        self.names.get(layer as usize).map(String::as_str)
+    }
+
+    /// Mask of the named layers, for `Collider::with_mask` and `QueryFilter::with_layers`
+    pub fn mask(&self, names: &[&str]) -> Result<u32> {
+        names
+            .iter()
+            .try_fold(0, |mask, name| Ok(mask | 1 << self.layer(name)?))
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        self.names.len() <= 1
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{entity::EntityId, Collider, ComponentRegistry, QueryFilter, Ray, Scene, Vector3};
+
+    fn add_sphere(scene: &mut Scene, position: Vector3, collider: Collider) -> EntityId {
+        let entity = scene
+            .add_entity(scene.get_root(), "sphere".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&entity)
+            .unwrap()
+            .set_position_global(position);
+        scene.add_collider(entity, collider).unwrap();
+        entity
+    }
+
+    #[test]
+    fn test_layer_table() {
+        let mut layers = CollisionLayers::new();
+        assert_eq!(layers.add("player").unwrap(), 1);
+        assert_eq!(layers.add("enemy").unwrap(), 2);
+        assert_eq!(layers.add("player").unwrap(), 1);
+        assert_eq!(layers.name(2), Some("enemy"));
+        assert_eq!(layers.mask(&["default", "enemy"]).unwrap(), 0b101);
+        assert!(matches!(
+            layers.mask(&["ghost"]),
+            Err(Error::LayerNotFound(name)) if name == "ghost"
+        ));
+        for i in 3..32 {
+            layers.add(&format!("layer {i}")).unwrap();
+        }
+        assert!(layers.add("one too many").is_err());
+        assert!(matches!(
+            Collider::new_sphere(vec3(0., 0., 0.), 0.5, true).with_layer(32),
+            Err(Error::InvalidLayer(32))
+        ));
+    }
+
+    #[test]
+    fn test_layers_filter_collisions_and_queries() {
+        let mut scene = Scene::new();
+        let layers = scene.collision_layers_mut();
+        let player = layers.add("player").unwrap();
+        let pickup = layers.add("pickup").unwrap();
+        let world = layers.mask(&["default", "player"]).unwrap();
+        let only_world = scene.collision_layers().mask(&["default"]).unwrap();
+
+        let sphere = |layer, mask| {
+            Collider::new_sphere(vec3(0., 0., 0.), 0.5, true)
+                .with_layer(layer)
+                .unwrap()
+                .with_mask(mask)
+        };
+        // a pickup doesn't block the player, but the wall does
+        let hero = add_sphere(&mut scene, vec3(0., 0., 0.), sphere(player, world));
+        let coin = add_sphere(&mut scene, vec3(0.5, 0., 0.), sphere(pickup, only_world));
+        let wall = add_sphere(&mut scene, vec3(-0.3, 0., 0.8), sphere(0, u32::MAX));
+        let zone = add_sphere(
+            &mut scene,
+            vec3(0.5, 0., 0.),
+            sphere(0, 1 << pickup).with_trigger(true),
+        );
+
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        let events = scene
+            .collision_events()
+            .iter()
+            .map(|(entity, e)| (*entity, e.other))
+            .collect::<Vec<_>>();
+        assert!(events.contains(&(hero, wall)));
+        assert!(!events.iter().any(|(a, b)| (*a, *b) == (hero, coin)));
+        let coin_position = scene.get_mut_transform(&coin).unwrap().position_global();
+        assert_eq!(coin_position, vec3(0.5, 0., 0.));
+        // the trigger only sees pickups
+        assert_eq!(scene.trigger_contents(&zone), vec![coin]);
+
+        // rays going through the coin to the hero
+        let ray = Ray::new(vec3(2., 0., 0.), vec3(-1., 0., 0.));
+        let hit = scene.raycast(&ray, 10., &QueryFilter::new()).unwrap();
+        assert_eq!(hit.entity, coin);
+        let filter = QueryFilter::new().with_layers(!(1 << pickup));
+        let hit = scene.raycast(&ray, 10., &filter).unwrap();
+        assert_eq!(hit.entity, hero);
+    }
+
+    #[test]
+    fn test_layers_saved_with_scene() {
+        let mut scene = Scene::new();
+        scene.collision_layers_mut().add("water").unwrap();
+        let registry = ComponentRegistry::new();
+        let loaded = Scene::from_ron(&scene.to_ron(&registry).unwrap(), &registry).unwrap();
+        assert_eq!(loaded.collision_layers().layer("water").unwrap(), 1);
+    }
+}
@@ -0,0 +1,551 @@
+use std::sync::OnceLock;
+
+use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{
+        aabb::ray_box,
+        broadphase::{Bounds, Bvh},
+        capsule::closest_points_segments,
+        obb::BoxFrame,
+        query::Ray,
+        shape::ColliderShape,
+    },
+    error::*,
+    model::{Mesh, Model},
+    transform::Transform,
+    Vector3,
+};
+
+/// Triangles of static geometry, e.g. a level loaded with `load_model` or a low-poly collision
+/// OBJ loaded with `load_collision_mesh`. The triangles are sorted in a BVH, so overlap tests
+/// and raycasts only look at the few of them near the other shape or along the ray
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriangleMesh {
+    vertices: Vec<[f32; 3]>,
+    // indices of the vertices of each triangle
+    triangles: Vec<[u32; 3]>,
+    // built on first use, in the space of the mesh
+    #[serde(skip)]
+    bvh: OnceLock<Bvh>,
+}
+
+/// A shape overlapping a mesh, in the world
+enum WorldShape {
+    Sphere(Vector3, f32),
+    Capsule(Vector3, Vector3, f32),
+    Box(BoxFrame),
+}
+
+impl TriangleMesh {
+    /// Fails if a triangle uses a vertex that doesn't exist
+    pub fn new(vertices: Vec<[f32; 3]>, triangles: Vec<[u32; 3]>) -> Result<Self> {
+        if let Some(idx) = triangles
+            .iter()
+            .flatten()
+            .find(|idx| **idx as usize >= vertices.len())
+        {
+            return Err(Error::Other(format!(
+                "a triangle uses vertex {idx}, but the mesh only has {} vertices",
+                vertices.len()
+            )));
+        }
+        Ok(Self {
+            vertices,
+            triangles,
+            bvh: OnceLock::new(),
+        })
+    }
+
+    /// The triangles of the mesh
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self> {
+        Self::from_meshes([mesh])
+    }
+
+    /// The triangles of all of the meshes of the model
+    pub fn from_model(model: &Model) -> Result<Self> {
+        Self::from_meshes(&model.meshes)
+    }
+
+    fn from_meshes<'a>(meshes: impl IntoIterator<Item = &'a Mesh>) -> Result<Self> {
+        let mut vertices = vec![];
+        let mut triangles = vec![];
+        for mesh in meshes {
+            let offset = vertices.len() as u32;
+            vertices.extend(mesh.vertices().iter().map(|v| v.position));
+            triangles.extend(
+                mesh.indices()
+                    .chunks_exact(3)
+                    .map(|t| [t[0] + offset, t[1] + offset, t[2] + offset]),
+            );
+        }
+        Self::new(vertices, triangles)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let items = (0..self.triangles.len())
+                .map(|idx| (idx, Bounds::around(self.triangle(idx))))
+                .collect();
+            Bvh::new(items)
+        })
+    }
+
+    /// Corners of the triangle, in the space of the mesh
+    fn triangle(&self, idx: usize) -> [Vector3; 3] {
+        self.triangles[idx].map(|v| self.vertices[v as usize].into())
+    }
+
+    fn world_triangle(&self, idx: usize, transform: &Transform) -> [Vector3; 3] {
+        self.triangle(idx).map(|corner| *transform * corner)
+    }
+
+    /// Box aligned with the world axis around the mesh
+    pub(crate) fn world_bounds(&self, transform: &Transform) -> Bounds {
+        match self.bvh().bounds() {
+            Some(bounds) => Bounds::around(corners(&bounds).map(|c| *transform * c)),
+            None => Bounds::new(transform.translation(), transform.translation()),
+        }
+    }
+
+    /// Triangles that may overlap the bounds, given in the world
+    fn triangles_near(&self, transform: &Transform, bounds: &Bounds) -> Vec<usize> {
+        let inverse = transform.inverse();
+        let local = Bounds::around(corners(bounds).map(|c| inverse * c));
+        self.bvh().query(&local)
+    }
+
+    /// Triangles that may overlap the bounds, in the world
+    pub(crate) fn world_triangles_near(
+        &self,
+        transform: &Transform,
+        bounds: &Bounds,
+    ) -> Vec<[Vector3; 3]> {
+        self.triangles_near(transform, bounds)
+            .into_iter()
+            .map(|idx| self.world_triangle(idx, transform))
+            .collect()
+    }
+
+    /// Distance along the ray to the closest triangle hit and its normal, facing the ray
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        // the tree is searched in the space of the mesh, the triangles hit in the world
+        let origin = transform.inverse_transform_point(ray.origin);
+        let direction = transform.inverse_transform_direction(ray.direction);
+        self.bvh()
+            .query_with(|bounds| ray_box(origin, direction, bounds.min, bounds.max).is_some())
+            .into_iter()
+            .filter_map(|idx| ray_triangle(ray, self.world_triangle(idx, transform)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Vector moving the other shape out of the mesh, None if they don't overlap. Each
+    /// triangle is tested on its own and the deepest correction is kept: the next passes
+    /// push the shape out of the others
+    pub(crate) fn correction_vec(
+        &self,
+        transform: &Transform,
+        other: &ColliderShape,
+        other_transform: &Transform,
+    ) -> Option<Vector3> {
+        let shape = match other {
+            ColliderShape::AABB(aabb) => {
+                let (min, max) = aabb.world_bounds(other_transform);
+                WorldShape::Box(BoxFrame::from_aabb(min, max))
+            }
+            ColliderShape::OBB(obb) => WorldShape::Box(obb.world(other_transform)),
+            ColliderShape::Sphere(sphere) => {
+                let (center, radius) = sphere.world(other_transform);
+                WorldShape::Sphere(center, radius)
+            }
+            ColliderShape::Capsule(capsule) => {
+                let (start, end, radius) = capsule.world(other_transform);
+                WorldShape::Capsule(start, end, radius)
+            }
+            // two meshes are both static
+            ColliderShape::Mesh(_) => return None,
+        };
+        self.triangles_near(transform, &other.world_bounds(other_transform))
+            .into_iter()
+            .filter_map(|idx| {
+                let triangle = self.world_triangle(idx, transform);
+                match &shape {
+                    WorldShape::Sphere(center, radius) => {
+                        sphere_triangle_correction(*center, *radius, triangle)
+                    }
+                    WorldShape::Capsule(start, end, radius) => {
+                        capsule_triangle_correction(*start, *end, *radius, triangle)
+                    }
+                    WorldShape::Box(frame) => box_triangle_correction(frame, triangle),
+                }
+            })
+            .max_by(|a, b| a.magnitude2().total_cmp(&b.magnitude2()))
+    }
+}
+
+/// The 8 corners of the bounds
+fn corners(bounds: &Bounds) -> [Vector3; 8] {
+    let (min, max) = (bounds.min, bounds.max);
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        Vector3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    })
+}
+
+/// Unit normal of the triangle, None if it has no area
+fn triangle_normal([a, b, c]: [Vector3; 3]) -> Option<Vector3> {
+    let normal = (b - a).cross(c - a);
+    let length = normal.magnitude();
+    (length > f32::EPSILON).then(|| normal / length)
+}
+
+/// Distance along the ray to the triangle and its normal, facing the ray (Möller–Trumbore)
+fn ray_triangle(ray: &Ray, [a, b, c]: [Vector3; 3]) -> Option<(f32, Vector3)> {
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(ac);
+    let determinant = ab.dot(p);
+    // the ray is parallel to the triangle
+    if determinant.abs() < 1e-8 {
+        return None;
+    }
+    let offset = ray.origin - a;
+    let u = offset.dot(p) / determinant;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let q = offset.cross(ab);
+    let v = ray.direction.dot(q) / determinant;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let distance = ac.dot(q) / determinant;
+    if distance < 0. {
+        return None;
+    }
+    let normal = triangle_normal([a, b, c])?;
+    let normal = if normal.dot(ray.direction) > 0. {
+        -normal
+    } else {
+        normal
+    };
+    Some((distance, normal))
+}
+
+/// Point of the triangle closest to `point` (from Real-Time Collision Detection)
+fn closest_point_triangle(point: Vector3, [a, b, c]: [Vector3; 3]) -> Vector3 {
+    let (ab, ac) = (b - a, c - a);
+    let ap = point - a;
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0. && d2 <= 0. {
+        return a;
+    }
+    let bp = point - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0. && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = point - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0. && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && d4 >= d3 && d5 >= d6 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    // inside the triangle
+    let denominator = va + vb + vc;
+    a + ab * (vb / denominator) + ac * (vc / denominator)
+}
+
+/// Vector moving the sphere out of the triangle, None if they don't overlap
+fn sphere_triangle_correction(
+    center: Vector3,
+    radius: f32,
+    triangle: [Vector3; 3],
+) -> Option<Vector3> {
+    let normal = triangle_normal(triangle)?;
+    let offset = center - closest_point_triangle(center, triangle);
+    let distance = offset.magnitude();
+    if distance >= radius {
+        return None;
+    }
+    let direction = if distance > 1e-6 {
+        offset / distance
+    } else {
+        // the center is on the triangle
+        normal
+    };
+    Some(direction * (radius - distance))
+}
+
+/// Vector moving the capsule out of the triangle, None if they don't overlap
+fn capsule_triangle_correction(
+    start: Vector3,
+    end: Vector3,
+    radius: f32,
+    triangle: [Vector3; 3],
+) -> Option<Vector3> {
+    let normal = triangle_normal(triangle)?;
+    let to_start = (start - triangle[0]).dot(normal);
+    let to_end = (end - triangle[0]).dot(normal);
+    if to_start * to_end < 0. {
+        let crossing = start + (end - start) * (to_start / (to_start - to_end));
+        if (closest_point_triangle(crossing, triangle) - crossing).magnitude2() < 1e-10 {
+            // the segment goes through the triangle: push its shorter side back out
+            let (deep, side) = if to_start.abs() < to_end.abs() {
+                (to_start, to_end.signum())
+            } else {
+                (to_end, to_start.signum())
+            };
+            return Some(normal * side * (radius + deep.abs()));
+        }
+    }
+
+    // otherwise the point of the segment closest to the triangle is one of its ends, or the
+    // closest to an edge
+    let mut points = vec![start, end];
+    for i in 0..3 {
+        let (on_segment, _) =
+            closest_points_segments(start, end, triangle[i], triangle[(i + 1) % 3]);
+        points.push(on_segment);
+    }
+    let closest = points
+        .into_iter()
+        .min_by(|a, b| {
+            let a = (closest_point_triangle(*a, triangle) - a).magnitude2();
+            let b = (closest_point_triangle(*b, triangle) - b).magnitude2();
+            a.total_cmp(&b)
+        })
+        .unwrap_or(start);
+    sphere_triangle_correction(closest, radius, triangle)
+}
+
+/// Vector moving the box out of the triangle, None if they don't overlap. Uses the separating
+/// axis theorem like `boxes_correction`, with the normal of the triangle, the axis of the box
+/// and the 9 cross products of their edges. The box is pushed to the side of the triangle its
+/// center is on
+fn box_triangle_correction(frame: &BoxFrame, triangle: [Vector3; 3]) -> Option<Vector3> {
+    let normal = triangle_normal(triangle)?;
+    let [a, b, c] = triangle;
+    let mut candidates = Vec::with_capacity(13);
+    candidates.push(normal);
+    candidates.extend(frame.axes);
+    for axis in frame.axes {
+        for edge in [b - a, c - b, a - c] {
+            candidates.push(axis.cross(edge));
+        }
+    }
+
+    let triangle_center = (a + b + c) / 3.;
+    let mut best: Option<(f32, Vector3)> = None;
+    for (idx, axis) in candidates.into_iter().enumerate() {
+        let length = axis.magnitude();
+        if length < 1e-5 {
+            continue;
+        }
+        let axis = axis / length;
+
+        let projections = triangle.map(|corner| corner.dot(axis));
+        let low = projections[0].min(projections[1]).min(projections[2]);
+        let high = projections[0].max(projections[1]).max(projections[2]);
+        let center = frame.center.dot(axis);
+        let radius = frame.projected_radius(axis);
+        if center + radius <= low || high <= center - radius {
+            return None;
+        }
+
+        let correction = if center >= triangle_center.dot(axis) {
+            axis * (high - (center - radius))
+        } else {
+            -axis * ((center + radius) - low)
+        };
+        const EDGE_BIAS: f32 = 1.05;
+        let overlap = correction.magnitude();
+        let score = if idx < 4 {
+            overlap
+        } else {
+            overlap * EDGE_BIAS
+        };
+        if best.is_none_or(|(best, _)| score < best) {
+            best = Some((score, correction));
+        }
+    }
+    best.map(|(_, correction)| correction)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::collision::{capsule::Capsule, obb::OrientedBoundingBox, sphere::Sphere};
+
+    /// A square floor of two triangles at y = 0, and a wall of two at x = 2
+    fn floor_and_wall() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                [-2., 0., -2.],
+                [2., 0., -2.],
+                [2., 0., 2.],
+                [-2., 0., 2.],
+                [2., 2., -2.],
+                [2., 2., 2.],
+            ],
+            vec![[0, 2, 1], [0, 3, 2], [1, 2, 5], [1, 5, 4]],
+        )
+        .unwrap()
+    }
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).magnitude() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_invalid_triangles() {
+        assert!(TriangleMesh::new(vec![[0., 0., 0.]; 3], vec![[0, 1, 3]]).is_err());
+    }
+
+    #[test]
+    fn test_mesh_corrections() {
+        let mesh = floor_and_wall();
+        let identity = Transform::identity();
+        let moved = |position: Vector3| Transform::from_translation(position);
+
+        // a sphere sunk into the floor goes straight up
+        let sphere = ColliderShape::Sphere(Sphere::new(vec3(0., 0., 0.), 0.5));
+        let correction = mesh
+            .correction_vec(&identity, &sphere, &moved(vec3(0.5, 0.3, 0.)))
+            .unwrap();
+        assert_near(correction, vec3(0., 0.2, 0.));
+        assert!(mesh
+            .correction_vec(&identity, &sphere, &moved(vec3(0.5, 0.6, 0.)))
+            .is_none());
+
+        // a lying capsule through the wall is pushed back on its side
+        let capsule =
+            ColliderShape::Capsule(Capsule::new(vec3(-0.5, 0., 0.), vec3(0.5, 0., 0.), 0.2));
+        let correction = mesh
+            .correction_vec(&identity, &capsule, &moved(vec3(1.8, 1., 0.5)))
+            .unwrap();
+        assert_near(correction, vec3(-0.5, 0., 0.));
+
+        // a box in the corner is pushed out of the side it's deepest in
+        let cube = ColliderShape::OBB(OrientedBoundingBox::new(
+            vec3(0., 0., 0.),
+            vec3(0.5, 0.5, 0.5),
+        ));
+        let correction = mesh
+            .correction_vec(&identity, &cube, &moved(vec3(1.6, 0.3, 0.)))
+            .unwrap();
+        assert_near(correction, vec3(0., 0.2, 0.));
+
+        // scaling the mesh moves the wall to x = 4
+        let scaled = Transform::from_parts(
+            vec3(0., 0., 0.),
+            cgmath::Matrix3::from_scale(1.),
+            vec3(2., 1., 2.),
+        );
+        assert!(mesh
+            .correction_vec(&scaled, &sphere, &moved(vec3(2.5, 1., 0.)))
+            .is_none());
+        let correction = mesh
+            .correction_vec(&scaled, &sphere, &moved(vec3(3.8, 1., 0.)))
+            .unwrap();
+        assert_near(correction, vec3(-0.3, 0., 0.));
+    }
+
+    #[test]
+    fn test_mesh_raycast() {
+        let mesh = floor_and_wall();
+        let transform = Transform::from_translation(vec3(0., 1., 0.));
+
+        let down = Ray::new(vec3(1., 3., 1.), vec3(0., -1., 0.));
+        let (distance, normal) = mesh.ray_intersection(&transform, &down).unwrap();
+        approx::assert_abs_diff_eq!(distance, 2., epsilon = 1e-5);
+        assert_near(normal, vec3(0., 1., 0.));
+
+        // from behind the wall, the normal faces the ray
+        let back = Ray::new(vec3(5., 2., 0.), vec3(-1., 0., 0.));
+        let (distance, normal) = mesh.ray_intersection(&transform, &back).unwrap();
+        approx::assert_abs_diff_eq!(distance, 3., epsilon = 1e-5);
+        assert_near(normal, vec3(1., 0., 0.));
+
+        // above the wall
+        let over = Ray::new(vec3(5., 3.5, 0.), vec3(-1., 0., 0.));
+        assert!(mesh.ray_intersection(&transform, &over).is_none());
+    }
+
+    #[test]
+    fn test_mesh_collider_in_scene() {
+        use std::time::Duration;
+
+        use crate::{model::Vertex, Collider, QueryFilter, RigidBody, Scene};
+
+        // the floor and the wall, as a model's mesh would have them
+        let source = floor_and_wall();
+        let vertices = source
+            .vertices
+            .iter()
+            .map(|position| Vertex {
+                position: *position,
+                uvs: [0., 0.],
+                normal: [0., 0., 0.],
+            })
+            .collect();
+        let indices = source.triangles.iter().flatten().copied().collect();
+        let mesh = Mesh::new("level".to_string(), vertices, indices, 0);
+        let triangles = TriangleMesh::from_mesh(&mesh).unwrap();
+        assert_eq!(triangles.triangle_count(), 4);
+
+        let mut scene = Scene::new();
+        let level = scene
+            .add_entity(scene.get_root(), "level".to_string())
+            .unwrap();
+        scene
+            .add_collider(level, Collider::new_mesh(triangles))
+            .unwrap();
+        let ball = scene
+            .add_entity(scene.get_root(), "ball".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .set_position_global(vec3(1., 2., 0.));
+        scene
+            .add_collider(ball, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+        let mut body = RigidBody::new(1.);
+        body.velocity = vec3(2., 0., 0.);
+        body.friction = 0.;
+        scene.add_component(ball, body).unwrap();
+
+        // the ball falls on the floor and rolls against the wall
+        for _ in 0..120 {
+            scene.on_update(Duration::from_millis(16)).unwrap();
+        }
+        let position = scene.get_mut_transform(&ball).unwrap().position_global();
+        assert_near(position, vec3(1.5, 0.5, 0.));
+
+        let ray = Ray::new(vec3(-1., 5., 0.3), vec3(0., -1., 0.));
+        let hit = scene.raycast(&ray, 10., &QueryFilter::new()).unwrap();
+        assert_eq!(hit.entity, level);
+        assert_near(hit.point, vec3(-1., 0., 0.3));
+    }
+}
@@ -1,8 +1,27 @@
 mod aabb;
 mod arena;
+mod broadphase;
+mod capsule;
 mod collider;
+mod contact;
+mod layers;
+mod mesh;
+mod obb;
+mod query;
 mod shape;
+mod sphere;
+mod sweep;
+mod trigger;
 
 pub use aabb::AxisAlignedBoundingBox;
 pub(crate) use arena::CollisionArena;
+pub use capsule::Capsule;
 pub use collider::Collider;
+pub use contact::{CollisionEvent, CollisionPhase};
+pub use layers::CollisionLayers;
+pub use mesh::TriangleMesh;
+pub use obb::OrientedBoundingBox;
+pub use query::{Overlap, QueryFilter, Ray, RayHit};
+pub use shape::ColliderShape;
+pub use sphere::Sphere;
+pub use trigger::TriggerEvent;
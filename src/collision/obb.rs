@@ -0,0 +1,290 @@
+use cgmath::{vec3, ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{
+        aabb::{ray_box, AxisAlignedBoundingBox},
+        capsule::{capsule_box_correction, Capsule},
+        query::Ray,
+        sphere::{sphere_box_correction, Sphere},
+    },
+    transform::Transform,
+    Vector3,
+};
+
+/// Oriented bounding box: a box in the entity's space, following its rotation.
+/// Slower than an AABB, but fits rotated objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrientedBoundingBox {
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub center: Vector3,
+    // half of the size along each local axis
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub half_extents: Vector3,
+}
+
+/// A box placed in the world
+pub(crate) struct BoxFrame {
+    pub center: Vector3,
+    // unit axis of the box
+    pub axes: [Vector3; 3],
+    half: Vector3,
+}
+
+impl BoxFrame {
+    pub fn from_aabb(min: Vector3, max: Vector3) -> Self {
+        Self {
+            center: (min + max) / 2.,
+            axes: [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()],
+            half: (max - min) / 2.,
+        }
+    }
+
+    /// Express a world point in the frame of the box
+    fn to_local(&self, point: Vector3) -> Vector3 {
+        let offset = point - self.center;
+        vec3(
+            offset.dot(self.axes[0]),
+            offset.dot(self.axes[1]),
+            offset.dot(self.axes[2]),
+        )
+    }
+
+    /// Express a world direction in the frame of the box
+    fn to_local_direction(&self, direction: Vector3) -> Vector3 {
+        vec3(
+            direction.dot(self.axes[0]),
+            direction.dot(self.axes[1]),
+            direction.dot(self.axes[2]),
+        )
+    }
+
+    /// Express a direction of the frame of the box in the world
+    fn to_world_direction(&self, direction: Vector3) -> Vector3 {
+        self.axes[0] * direction.x + self.axes[1] * direction.y + self.axes[2] * direction.z
+    }
+
+    /// The 8 corners of the box
+    pub fn corners(&self) -> [Vector3; 8] {
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|i: usize| {
+            (0..3)
+                .map(|axis| {
+                    let sign = if i & (1 << axis) == 0 { -1. } else { 1. };
+                    self.axes[axis] * self.half[axis] * sign
+                })
+                .fold(self.center, |corner, offset| corner + offset)
+        })
+    }
+
+    /// Half of the length of the box projected on `axis`
+    pub fn projected_radius(&self, axis: Vector3) -> f32 {
+        (0..3)
+            .map(|i| (self.half[i] * self.axes[i].dot(axis)).abs())
+            .sum()
+    }
+}
+
+impl OrientedBoundingBox {
+    pub fn new(center: Vector3, half_extents: Vector3) -> Self {
+        Self {
+            center,
+            half_extents,
+        }
+    }
+
+    /// The box in the world. Scale is applied along the box axis, which is exact unless a
+    /// parent with non-uniform scale is rotated
+    pub(crate) fn world(&self, transform: &Transform) -> BoxFrame {
+        let rotation = transform.rotation_matrix();
+        BoxFrame {
+            center: *transform * self.center,
+            axes: [rotation.x, rotation.y, rotation.z],
+            half: self
+                .half_extents
+                .mul_element_wise(transform.scale())
+                .map(f32::abs),
+        }
+    }
+
+    /// Distance along the ray to the box and normal of the face hit, None if it's missed
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        let frame = self.world(transform);
+        // the axis are orthonormal, so distances are the same in the frame of the box
+        let (distance, normal) = ray_box(
+            frame.to_local(ray.origin),
+            frame.to_local_direction(ray.direction),
+            -frame.half,
+            frame.half,
+        )?;
+        Some((distance, frame.to_world_direction(normal)))
+    }
+
+    /// Center of the box in the world, and half of its size along each world axis
+    pub(crate) fn world_reach(&self, transform: &Transform) -> (Vector3, Vector3) {
+        let frame = self.world(transform);
+        let reach = vec3(
+            frame.projected_radius(Vector3::unit_x()),
+            frame.projected_radius(Vector3::unit_y()),
+            frame.projected_radius(Vector3::unit_z()),
+        );
+        (frame.center, reach)
+    }
+
+    /// Get the collision correction vector for this box if the other shape is also an OBB
+    pub fn obb_correction_vec(
+        a: &OrientedBoundingBox,
+        a_transform: &Transform,
+        b: &OrientedBoundingBox,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        boxes_correction(&a.world(a_transform), &b.world(b_transform))
+    }
+
+    /// Get the collision correction vector for this box if the other shape is an AABB
+    pub fn aabb_correction_vec(
+        a: &OrientedBoundingBox,
+        a_transform: &Transform,
+        b: &AxisAlignedBoundingBox,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (min, max) = b.world_bounds(b_transform);
+        boxes_correction(&a.world(a_transform), &BoxFrame::from_aabb(min, max))
+    }
+
+    /// Get the collision correction vector for this box if the other shape is a sphere
+    pub fn sphere_correction_vec(
+        a: &OrientedBoundingBox,
+        a_transform: &Transform,
+        b: &Sphere,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let frame = a.world(a_transform);
+        let (center, radius) = b.world(b_transform);
+        // moves the sphere out of the box, in the box's frame
+        let correction =
+            sphere_box_correction(frame.to_local(center), radius, -frame.half, frame.half)?;
+        Some(-frame.to_world_direction(correction))
+    }
+
+    /// Get the collision correction vector for this box if the other shape is a capsule
+    pub fn capsule_correction_vec(
+        a: &OrientedBoundingBox,
+        a_transform: &Transform,
+        b: &Capsule,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let frame = a.world(a_transform);
+        let (start, end, radius) = b.world(b_transform);
+        // moves the capsule out of the box, in the box's frame
+        let correction = capsule_box_correction(
+            frame.to_local(start),
+            frame.to_local(end),
+            radius,
+            -frame.half,
+            frame.half,
+        )?;
+        Some(-frame.to_world_direction(correction))
+    }
+}
+
+/// Vector moving the box a out of the box b, using the separating axis theorem: the boxes
+/// overlap only if their projections overlap on all 15 candidate axis. The correction is along
+/// the axis of least overlap. None if they don't overlap
+pub(crate) fn boxes_correction(a: &BoxFrame, b: &BoxFrame) -> Option<Vector3> {
+    // face axis come first, and are preferred over edge axis on ties
+    let mut candidates = Vec::with_capacity(15);
+    candidates.extend(a.axes);
+    candidates.extend(b.axes);
+    for a_axis in a.axes {
+        for b_axis in b.axes {
+            candidates.push(a_axis.cross(b_axis));
+        }
+    }
+
+    let offset = b.center - a.center;
+    let mut best: Option<(f32, Vector3)> = None;
+    for (idx, axis) in candidates.into_iter().enumerate() {
+        let length = axis.magnitude();
+        // parallel edges give no axis, their faces are already tested
+        if length < 1e-5 {
+            continue;
+        }
+        let axis = axis / length;
+
+        let distance = offset.dot(axis);
+        let overlap = a.projected_radius(axis) + b.projected_radius(axis) - distance.abs();
+        if overlap <= 0. {
+            return None;
+        }
+
+        const EDGE_BIAS: f32 = 1.05;
+        let score = if idx < 6 {
+            overlap
+        } else {
+            overlap * EDGE_BIAS
+        };
+        if best.is_none_or(|(best, _)| score < best) {
+            // away from b
+            let direction = if distance > 0. { -axis } else { axis };
+            best = Some((score, direction * overlap));
+        }
+    }
+    best.map(|(_, correction)| correction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_box_correction_vec() {
+        let obb = OrientedBoundingBox::new(vec3(0., 0., 0.), vec3(1., 1., 1.));
+        let aabb = AxisAlignedBoundingBox::new(vec3(-1., -1., -1.), vec3(1., 1., 1.));
+        let aabb_transform = Transform::from_translation(vec3(2.3, 0., 0.));
+
+        // unrotated, the boxes are apart
+        assert!(OrientedBoundingBox::aabb_correction_vec(
+            &obb,
+            &Transform::identity(),
+            &aabb,
+            &aabb_transform
+        )
+        .is_none());
+
+        // rotated by 45 degrees, the corner reaches sqrt(2) ~ 1.414 along x
+        let rotated = Transform::from_angle_z(std::f32::consts::FRAC_PI_4);
+        let vec = OrientedBoundingBox::aabb_correction_vec(&obb, &rotated, &aabb, &aabb_transform)
+            .unwrap();
+        approx::assert_abs_diff_eq!(vec.x, -(2f32.sqrt() + 1. - 2.3), epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(vec.y, 0., epsilon = 1e-4);
+
+        let other = OrientedBoundingBox::new(vec3(0., 0., 0.), vec3(1., 1., 1.));
+        let vec = OrientedBoundingBox::obb_correction_vec(
+            &obb,
+            &rotated,
+            &other,
+            &Transform::from_translation(vec3(0., 2.3, 0.)),
+        )
+        .unwrap();
+        approx::assert_abs_diff_eq!(vec.y, -(2f32.sqrt() + 1. - 2.3), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_rotated_box_round_shapes() {
+        let obb = OrientedBoundingBox::new(vec3(0., 0., 0.), vec3(2., 0.5, 2.));
+        // tilted by 90 degrees around z: now 4 tall and 1 wide
+        let tilted = Transform::from_angle_z(std::f32::consts::FRAC_PI_2);
+
+        let sphere = Sphere::new(vec3(0., 0., 0.), 0.5);
+        let beside = Transform::from_translation(vec3(0.75, 1.5, 0.));
+        let vec =
+            OrientedBoundingBox::sphere_correction_vec(&obb, &tilted, &sphere, &beside).unwrap();
+        approx::assert_abs_diff_eq!(vec.x, -0.25, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(vec.y, 0., epsilon = 1e-4);
+
+        let capsule = Capsule::new(vec3(0., -1., 0.), vec3(0., 1., 0.), 0.5);
+        let vec =
+            OrientedBoundingBox::capsule_correction_vec(&obb, &tilted, &capsule, &beside).unwrap();
+        approx::assert_abs_diff_eq!(vec.x, -0.25, epsilon = 1e-3);
+    }
+}
@@ -0,0 +1,377 @@
+use cgmath::InnerSpace;
+
+use crate::{component::ComponentId, entity::EntityId, Collider, Vector3};
+
+/// Half-line from `origin`, along the unit vector `direction`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+impl Ray {
+    /// Construct a ray, normalizing `direction`
+    pub fn new(origin: Vector3, direction: Vector3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn point_at(&self, distance: f32) -> Vector3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// A collider hit by a ray or a shape cast
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit {
+    pub entity: EntityId,
+    /// Distance travelled by the ray, or by the shape, before the hit
+    pub distance: f32,
+    /// Point of the collider's surface that was hit
+    pub point: Vector3,
+    /// Unit normal of the collider's surface at the hit, facing the ray
+    pub normal: Vector3,
+}
+
+/// A collider found by an overlap query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    pub entity: EntityId,
+    /// The collider component
+    pub collider: ComponentId,
+}
+
+/// Which colliders spatial queries consider
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryFilter {
+    /// Bit mask of the collider layers to consider
+    pub layers: u32,
+    /// Entities whose colliders are ignored, e.g. the entity casting the ray
+    pub exclude: Vec<EntityId>,
+    /// Whether trigger colliders can be hit
+    pub include_triggers: bool,
+}
+
+impl QueryFilter {
+    pub fn new() -> Self {
+        Self {
+            layers: u32::MAX,
+            exclude: vec![],
+            include_triggers: false,
+        }
+    }
+
+    /// Only consider colliders on the layers of the mask
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn excluding(mut self, entity: EntityId) -> Self {
+        self.exclude.push(entity);
+        self
+    }
+
+    pub fn with_triggers(mut self, include_triggers: bool) -> Self {
+        self.include_triggers = include_triggers;
+        self
+    }
+
+    pub(crate) fn accepts(&self, collider: &Collider, entity: &EntityId) -> bool {
+        self.layers & collider.layer_bit() != 0
+            && (self.include_triggers || !collider.is_trigger())
+            && !self.exclude.contains(entity)
+    }
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{transform::Transform, ColliderShape, Scene, Sphere};
+
+    fn scene_with_targets() -> (Scene, EntityId, EntityId, EntityId) {
+        let mut scene = Scene::new();
+        let add = |scene: &mut Scene, name: &str, position: Vector3, collider: Collider| {
+            let entity = scene
+                .add_entity(scene.get_root(), name.to_string())
+                .unwrap();
+            scene
+                .get_mut_transform(&entity)
+                .unwrap()
+                .set_position_global(position);
+            scene.add_collider(entity, collider).unwrap();
+            entity
+        };
+        let wall = add(
+            &mut scene,
+            "wall",
+            vec3(5., 0., 0.),
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 2., 2.), false),
+        );
+        let ball = add(
+            &mut scene,
+            "ball",
+            vec3(2., 0., 0.),
+            Collider::new_sphere(vec3(0., 0., 0.), 0.5, false)
+                .with_layer(1)
+                .unwrap(),
+        );
+        let pillar = add(
+            &mut scene,
+            "pillar",
+            vec3(8., 0., 0.),
+            Collider::new_capsule(vec3(0., -1., 0.), vec3(0., 1., 0.), 0.5, false),
+        );
+        // propagate the positions
+        scene.on_update(Duration::ZERO).unwrap();
+        (scene, wall, ball, pillar)
+    }
+
+    #[test]
+    fn test_raycast() {
+        let (scene, wall, ball, pillar) = scene_with_targets();
+        let ray = Ray::new(vec3(0., 0., 0.), vec3(1., 0., 0.));
+
+        let hit = scene.raycast(&ray, 100., &QueryFilter::new()).unwrap();
+        assert_eq!(hit.entity, ball);
+        approx::assert_abs_diff_eq!(hit.distance, 1.5, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(hit.point.x, 1.5, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(hit.normal.x, -1., epsilon = 1e-5);
+
+        let hits = scene.raycast_all(&ray, 100., &QueryFilter::new());
+        let entities = hits.iter().map(|h| h.entity).collect::<Vec<_>>();
+        assert_eq!(entities, vec![ball, wall, pillar]);
+        approx::assert_abs_diff_eq!(hits[1].distance, 4.5, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(hits[2].distance, 7.5, epsilon = 1e-5);
+
+        // the ball is on layer 1, only layer 0 is considered
+        let hit = scene
+            .raycast(&ray, 100., &QueryFilter::new().with_layers(1 << 0))
+            .unwrap();
+        assert_eq!(hit.entity, wall);
+        // too short to reach the wall
+        let hits = scene.raycast_all(&ray, 4., &QueryFilter::new().excluding(ball));
+        assert!(hits.is_empty());
+
+        // going up, above everything
+        let up = Ray::new(vec3(0., 0., 0.), vec3(0., 1., 0.));
+        assert!(scene.raycast(&up, 100., &QueryFilter::new()).is_none());
+    }
+
+    #[test]
+    fn test_shape_cast() {
+        let (scene, wall, ball, _) = scene_with_targets();
+        let shape = ColliderShape::Sphere(Sphere::new(vec3(0., 0., 0.), 0.25));
+        let start = Transform::from_translation(vec3(0., 0., 0.));
+
+        let hit = scene
+            .shape_cast(&shape, &start, vec3(1., 0., 0.), 100., &QueryFilter::new())
+            .unwrap();
+        assert_eq!(hit.entity, ball);
+        // the spheres touch when their centers are 0.75 apart
+        approx::assert_abs_diff_eq!(hit.distance, 1.25, epsilon = 1e-3);
+        approx::assert_abs_diff_eq!(hit.normal.x, -1., epsilon = 1e-3);
+        approx::assert_abs_diff_eq!(hit.point.x, 1.5, epsilon = 1e-3);
+
+        let filter = QueryFilter::new().excluding(ball);
+        let hit = scene
+            .shape_cast(&shape, &start, vec3(1., 0., 0.), 100., &filter)
+            .unwrap();
+        assert_eq!(hit.entity, wall);
+        approx::assert_abs_diff_eq!(hit.distance, 4.25, epsilon = 1e-3);
+
+        // passing above the ball, then too short to reach the wall
+        let above = Transform::from_translation(vec3(0., 0.8, 0.));
+        assert!(scene
+            .shape_cast(&shape, &above, vec3(1., 0., 0.), 4., &QueryFilter::new())
+            .is_none());
+    }
+
+    #[test]
+    fn test_queries_find_moved_colliders() {
+        let (mut scene, _, ball, _) = scene_with_targets();
+        let cube = scene
+            .add_entity(scene.get_root(), "cube".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                cube,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(0.25, 0.25, 0.25), true),
+            )
+            .unwrap();
+        let zone = scene
+            .add_entity(scene.get_root(), "zone".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                zone,
+                Collider::new_sphere(vec3(0., 0., 0.), 0.25, false).with_trigger(true),
+            )
+            .unwrap();
+        scene.on_update(Duration::ZERO).unwrap();
+
+        // both moved into the way since the last update
+        for (entity, x) in [(cube, 3.5), (zone, 1.)] {
+            scene
+                .get_mut_transform(&entity)
+                .unwrap()
+                .set_position_global(vec3(x, 0., 0.));
+        }
+        let ray = Ray::new(vec3(0., 0., 0.), vec3(1., 0., 0.));
+        let filter = QueryFilter::new().excluding(ball);
+        let hit = scene.raycast(&ray, 100., &filter).unwrap();
+        assert_eq!(hit.entity, cube);
+        approx::assert_abs_diff_eq!(hit.distance, 3.25, epsilon = 1e-5);
+        let hit = scene
+            .raycast(&ray, 100., &filter.clone().with_triggers(true))
+            .unwrap();
+        assert_eq!(hit.entity, zone);
+        approx::assert_abs_diff_eq!(hit.distance, 0.75, epsilon = 1e-5);
+
+        let shape = ColliderShape::Sphere(Sphere::new(vec3(0., 0., 0.), 0.25));
+        let start = Transform::from_translation(vec3(0., 0., 0.));
+        let hit = scene
+            .shape_cast(&shape, &start, vec3(1., 0., 0.), 100., &filter)
+            .unwrap();
+        assert_eq!(hit.entity, cube);
+        approx::assert_abs_diff_eq!(hit.distance, 3., epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_queries_find_moved_static_colliders() {
+        let (mut scene, wall, ball, pillar) = scene_with_targets();
+        // moved behind the ray's origin since the last update
+        scene
+            .get_mut_transform(&wall)
+            .unwrap()
+            .set_position_global(vec3(-5., 0., 0.));
+
+        let filter = QueryFilter::new().excluding(ball);
+        let hit = scene
+            .raycast(&Ray::new(vec3(0., 0., 0.), vec3(1., 0., 0.)), 100., &filter)
+            .unwrap();
+        assert_eq!(hit.entity, pillar);
+        approx::assert_abs_diff_eq!(hit.distance, 7.5, epsilon = 1e-5);
+        let hit = scene
+            .raycast(
+                &Ray::new(vec3(0., 0., 0.), vec3(-1., 0., 0.)),
+                100.,
+                &filter,
+            )
+            .unwrap();
+        assert_eq!(hit.entity, wall);
+        approx::assert_abs_diff_eq!(hit.distance, 4.5, epsilon = 1e-5);
+
+        let entities =
+            |overlaps: Vec<Overlap>| overlaps.into_iter().map(|o| o.entity).collect::<Vec<_>>();
+        let half_extents = vec3(0.1, 0.1, 0.1);
+        let at_old_place = scene.overlap_box(vec3(5., 0., 0.), half_extents, &filter);
+        assert!(at_old_place.is_empty());
+        let at_new_place = scene.overlap_box(vec3(-5., 0., 0.), half_extents, &filter);
+        assert_eq!(entities(at_new_place), vec![wall]);
+
+        // the same once the hierarchies are rebuilt
+        scene.on_update(Duration::ZERO).unwrap();
+        assert!(scene
+            .overlap_box(vec3(5., 0., 0.), half_extents, &filter)
+            .is_empty());
+        let at_new_place = scene.overlap_box(vec3(-5., 0., 0.), half_extents, &filter);
+        assert_eq!(entities(at_new_place), vec![wall]);
+    }
+
+    #[test]
+    fn test_overlap_queries() {
+        let (mut scene, wall, ball, pillar) = scene_with_targets();
+        let entities = |overlaps: Vec<Overlap>| {
+            let mut entities = overlaps.iter().map(|o| o.entity).collect::<Vec<_>>();
+            entities.sort();
+            entities
+        };
+        let sorted = |mut entities: Vec<EntityId>| {
+            entities.sort();
+            entities
+        };
+
+        // reaching the ball and the wall, 1 away from the center
+        let all = QueryFilter::new();
+        let found = scene.overlap_sphere(vec3(3.5, 0., 0.), 1.2, &all);
+        assert_eq!(entities(found.clone()), sorted(vec![ball, wall]));
+        let ball_overlap = found.iter().find(|o| o.entity == ball).unwrap();
+        assert!(scene
+            .get_ref_component::<Collider>(&ball_overlap.collider)
+            .is_some());
+        let found = scene.overlap_sphere(vec3(3.5, 0., 0.), 1.2, &all.clone().with_layers(1));
+        assert_eq!(entities(found), vec![wall]);
+        assert!(scene
+            .overlap_sphere(vec3(3.5, 0., 0.), 0.9, &all)
+            .is_empty());
+
+        // above the pillar, then around its top
+        assert!(scene
+            .overlap_box(vec3(8., 2.5, 0.), vec3(0.5, 0.2, 0.5), &all)
+            .is_empty());
+        let found = scene.overlap_box(vec3(8., 1.4, 0.), vec3(0.5, 0.2, 0.5), &all);
+        assert_eq!(entities(found), vec![pillar]);
+
+        // a collider added since the last update, and a trigger only found on demand
+        let mine = scene
+            .add_entity(scene.get_root(), "mine".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                mine,
+                Collider::new_sphere(vec3(3., 3., 0.), 0.5, false).with_trigger(true),
+            )
+            .unwrap();
+        let barrel = scene
+            .add_entity(scene.get_root(), "barrel".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                barrel,
+                Collider::new_aabb(vec3(3., 2., 0.), vec3(0.5, 0.5, 0.5), false),
+            )
+            .unwrap();
+        let found = scene.overlap_sphere(vec3(3., 2.5, 0.), 0.2, &all);
+        assert_eq!(entities(found), vec![barrel]);
+        let filter = QueryFilter::new().with_triggers(true).excluding(barrel);
+        let found = scene.overlap_sphere(vec3(3., 2.5, 0.), 0.2, &filter);
+        assert_eq!(entities(found), vec![mine]);
+
+        // a dynamic collider and the trigger, found where they moved since the last update.
+        // The trigger's sphere is off its entity, at (0, 3)
+        let drone = scene
+            .add_entity(scene.get_root(), "drone".to_string())
+            .unwrap();
+        scene
+            .add_collider(drone, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+        scene.on_update(Duration::ZERO).unwrap();
+        for (entity, position) in [(drone, vec3(0., 6., 0.)), (mine, vec3(-3., 0., 0.))] {
+            scene
+                .get_mut_transform(&entity)
+                .unwrap()
+                .set_position_global(position);
+        }
+        let filter = QueryFilter::new().with_triggers(true);
+        let found = scene.overlap_box(vec3(0., 6., 0.), vec3(1., 0.5, 1.), &filter);
+        assert_eq!(entities(found), vec![drone]);
+        let found = scene.overlap_sphere(vec3(0., 3., 0.), 0.6, &filter);
+        assert_eq!(entities(found), vec![mine]);
+        assert!(scene
+            .overlap_sphere(vec3(0., 0., 0.), 0.6, &filter)
+            .is_empty());
+    }
+}
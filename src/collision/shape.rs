@@ -1,22 +1,133 @@
-use crate::{transform::Transform, Vector3};
+use serde::{Deserialize, Serialize};
 
-use super::aabb::AxisAlignedBoundingBox;
+use crate::{
+    collision::{broadphase::Bounds, sphere::max_scale},
+    transform::Transform,
+    Vector3,
+};
 
+use super::{
+    aabb::AxisAlignedBoundingBox, capsule::Capsule, mesh::TriangleMesh, obb::OrientedBoundingBox,
+    query::Ray, sphere::Sphere,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ColliderShape {
     AABB(AxisAlignedBoundingBox),
+    OBB(OrientedBoundingBox),
+    Sphere(Sphere),
+    Capsule(Capsule),
+    /// Only for static colliders: meshes don't collide with each other
+    Mesh(TriangleMesh),
 }
 
 impl ColliderShape {
+    /// Get the vector that moves `a` out of `b`, None if they don't overlap
     pub fn get_correction_vec(
         a: &Self,
         a_transform: &Transform,
         b: &Self,
         b_transform: &Transform,
     ) -> Option<Vector3> {
+        use ColliderShape::*;
         match (a, b) {
-            (ColliderShape::AABB(a), ColliderShape::AABB(b)) => {
+            (Mesh(_), Mesh(_)) => None,
+            (_, Mesh(b)) => b.correction_vec(b_transform, a, a_transform),
+            (Mesh(_), _) => Self::get_correction_vec(b, b_transform, a, a_transform).map(|v| -v),
+            (AABB(a), AABB(b)) => {
                 AxisAlignedBoundingBox::aabb_correction_vec(a, a_transform, b, b_transform)
             }
+            (Sphere(a), Sphere(b)) => {
+                self::Sphere::sphere_correction_vec(a, a_transform, b, b_transform)
+            }
+            (Capsule(a), Capsule(b)) => {
+                self::Capsule::capsule_correction_vec(a, a_transform, b, b_transform)
+            }
+            (OBB(a), OBB(b)) => {
+                OrientedBoundingBox::obb_correction_vec(a, a_transform, b, b_transform)
+            }
+            (OBB(a), AABB(b)) => {
+                OrientedBoundingBox::aabb_correction_vec(a, a_transform, b, b_transform)
+            }
+            (OBB(a), Sphere(b)) => {
+                OrientedBoundingBox::sphere_correction_vec(a, a_transform, b, b_transform)
+            }
+            (OBB(a), Capsule(b)) => {
+                OrientedBoundingBox::capsule_correction_vec(a, a_transform, b, b_transform)
+            }
+            (Sphere(a), AABB(b)) => {
+                self::Sphere::aabb_correction_vec(a, a_transform, b, b_transform)
+            }
+            (Capsule(a), AABB(b)) => {
+                self::Capsule::aabb_correction_vec(a, a_transform, b, b_transform)
+            }
+            (Capsule(a), Sphere(b)) => {
+                self::Capsule::sphere_correction_vec(a, a_transform, b, b_transform)
+            }
+            // the same pairs the other way around: move b out of a, and flip it
+            (AABB(_) | Sphere(_) | Capsule(_), OBB(_))
+            | (AABB(_), Sphere(_) | Capsule(_))
+            | (Sphere(_), Capsule(_)) => {
+                Self::get_correction_vec(b, b_transform, a, a_transform).map(|v| -v)
+            }
+        }
+    }
+
+    /// Distance along the ray to the shape and normal where it's hit, None if it's missed
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        match self {
+            ColliderShape::AABB(aabb) => aabb.ray_intersection(transform, ray),
+            ColliderShape::OBB(obb) => obb.ray_intersection(transform, ray),
+            ColliderShape::Sphere(sphere) => sphere.ray_intersection(transform, ray),
+            ColliderShape::Capsule(capsule) => capsule.ray_intersection(transform, ray),
+            ColliderShape::Mesh(mesh) => mesh.ray_intersection(transform, ray),
+        }
+    }
+
+    /// Box aligned with the world axis around the shape
+    pub(crate) fn world_bounds(&self, transform: &Transform) -> Bounds {
+        match self {
+            ColliderShape::AABB(aabb) => {
+                let (min, max) = aabb.world_bounds(transform);
+                Bounds::new(min, max)
+            }
+            ColliderShape::OBB(obb) => {
+                let (center, reach) = obb.world_reach(transform);
+                Bounds::new(center - reach, center + reach)
+            }
+            ColliderShape::Sphere(sphere) => {
+                let (center, radius) = sphere.world(transform);
+                let reach = Vector3::new(radius, radius, radius);
+                Bounds::new(center - reach, center + reach)
+            }
+            ColliderShape::Capsule(capsule) => {
+                let (start, end, radius) = capsule.world(transform);
+                let reach = Vector3::new(radius, radius, radius);
+                Bounds::around([start - reach, start + reach, end - reach, end + reach])
+            }
+            ColliderShape::Mesh(mesh) => mesh.world_bounds(transform),
+        }
+    }
+
+    /// Smallest width of the shape in the world, in any direction
+    pub(crate) fn thickness(&self, transform: &Transform) -> f32 {
+        let scale = transform.scale().map(f32::abs);
+        match self {
+            ColliderShape::AABB(aabb) => {
+                let scaled = aabb.scaled(scale);
+                let size = scaled.max - scaled.min;
+                size.x.min(size.y).min(size.z)
+            }
+            ColliderShape::OBB(obb) => {
+                let half = obb.half_extents.map(f32::abs);
+                2. * (half.x * scale.x)
+                    .min(half.y * scale.y)
+                    .min(half.z * scale.z)
+            }
+            ColliderShape::Sphere(sphere) => 2. * sphere.radius * max_scale(transform),
+            ColliderShape::Capsule(capsule) => 2. * capsule.radius * max_scale(transform),
+            // triangles have no thickness
+            ColliderShape::Mesh(_) => 0.,
         }
     }
 
@@ -30,6 +141,79 @@ impl ColliderShape {
             (ColliderShape::AABB(aabb), ColliderShape::AABB(other_aabb)) => {
                 AxisAlignedBoundingBox::contains_aabb(aabb, transform, other_aabb, other_transform)
             }
+            _ => Self::get_correction_vec(self, transform, other, other_transform).is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use crate::{entity::transform::TransformComponent, Collider, Scene};
+
+    #[test]
+    fn test_round_shapes_in_collider_pass() {
+        let mut scene = Scene::new();
+        let floor = scene
+            .add_entity(scene.get_root(), "floor".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                floor,
+                Collider::new_aabb(vec3(0., -1., 0.), vec3(5., 1., 5.), false),
+            )
+            .unwrap();
+
+        let ball = scene
+            .add_entity(scene.get_root(), "ball".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .set_position_global(vec3(0., 0.4, 0.));
+        scene
+            .add_collider(ball, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+
+        let pill = scene
+            .add_entity(scene.get_root(), "pill".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&pill)
+            .unwrap()
+            .set_position_global(vec3(3., 0., 0.));
+        scene
+            .add_collider(
+                pill,
+                Collider::new_capsule(vec3(0., 0.3, 0.), vec3(0., 1.3, 0.), 0.5, true),
+            )
+            .unwrap();
+
+        // a child inside the floor, whose parent then moves it away from the floor
+        let holder = scene
+            .add_entity(scene.get_root(), "holder".to_string())
+            .unwrap();
+        let carried = scene.add_entity(holder, "carried".to_string()).unwrap();
+        scene
+            .add_collider(carried, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+        scene
+            .get_mut_transform(&holder)
+            .unwrap()
+            .set_position_global(vec3(10., 0., 0.));
+
+        // colliders are resolved with the transforms set before the update, propagated to children
+        scene.on_update(Duration::ZERO).unwrap();
+
+        for (entity, y) in [(ball, 0.5), (pill, 0.2), (carried, 0.)] {
+            let transform = scene.get_transform(&entity).unwrap();
+            let transform = scene
+                .get_ref_component::<TransformComponent>(&transform)
+                .unwrap();
+            approx::assert_abs_diff_eq!(transform.position_global().y, y, epsilon = 1e-3);
         }
     }
 }
@@ -0,0 +1,190 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{aabb::AxisAlignedBoundingBox, query::Ray},
+    transform::Transform,
+    Vector3,
+};
+
+/// Sphere around a center, relative to the entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sphere {
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vector3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Center and radius in the world. The radius grows with the largest scale of the transform
+    pub(crate) fn world(&self, transform: &Transform) -> (Vector3, f32) {
+        (*transform * self.center, self.radius * max_scale(transform))
+    }
+
+    /// Distance along the ray to the sphere and normal where it's hit, None if it's missed
+    pub fn ray_intersection(&self, transform: &Transform, ray: &Ray) -> Option<(f32, Vector3)> {
+        let (center, radius) = self.world(transform);
+        ray_sphere(ray.origin, ray.direction, center, radius)
+    }
+
+    /// Get the collision correction vector for this sphere if the other shape is also a sphere
+    pub fn sphere_correction_vec(
+        a: &Sphere,
+        a_transform: &Transform,
+        b: &Sphere,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (a_center, a_radius) = a.world(a_transform);
+        let (b_center, b_radius) = b.world(b_transform);
+        spheres_correction(a_center, a_radius, b_center, b_radius)
+    }
+
+    /// Get the collision correction vector for this sphere if the other shape is an AABB
+    pub fn aabb_correction_vec(
+        a: &Sphere,
+        a_transform: &Transform,
+        b: &AxisAlignedBoundingBox,
+        b_transform: &Transform,
+    ) -> Option<Vector3> {
+        let (center, radius) = a.world(a_transform);
+        let (min, max) = b.world_bounds(b_transform);
+        sphere_box_correction(center, radius, min, max)
+    }
+}
+
+/// Largest scale along the axis of a transform
+pub(crate) fn max_scale(transform: &Transform) -> f32 {
+    let scale = transform.scale();
+    scale.x.abs().max(scale.y.abs()).max(scale.z.abs())
+}
+
+/// Distance along the ray, of unit `direction`, to the sphere and normal where it's hit.
+/// A ray starting inside of the sphere hits it right away, facing the ray
+pub(crate) fn ray_sphere(
+    origin: Vector3,
+    direction: Vector3,
+    center: Vector3,
+    radius: f32,
+) -> Option<(f32, Vector3)> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.magnitude2() - radius * radius;
+    if c <= 0. {
+        return Some((0., -direction));
+    }
+    // outside, and going away
+    if b > 0. {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0. {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    let normal = (origin + direction * distance - center) / radius;
+    Some((distance, normal))
+}
+
+/// Vector moving the sphere a out of the sphere b, None if they don't overlap
+pub(crate) fn spheres_correction(
+    a_center: Vector3,
+    a_radius: f32,
+    b_center: Vector3,
+    b_radius: f32,
+) -> Option<Vector3> {
+    let offset = a_center - b_center;
+    let distance = offset.magnitude();
+    let depth = a_radius + b_radius - distance;
+    if depth <= 0. {
+        return None;
+    }
+    // same centers: any direction works, push up
+    let normal = if distance > f32::EPSILON {
+        offset / distance
+    } else {
+        Vector3::unit_y()
+    };
+    Some(normal * depth)
+}
+
+/// Vector moving the sphere out of the box, None if they don't overlap
+pub(crate) fn sphere_box_correction(
+    center: Vector3,
+    radius: f32,
+    min: Vector3,
+    max: Vector3,
+) -> Option<Vector3> {
+    let closest = vec3(
+        center.x.clamp(min.x, max.x),
+        center.y.clamp(min.y, max.y),
+        center.z.clamp(min.z, max.z),
+    );
+    let offset = center - closest;
+    let distance = offset.magnitude();
+    if distance > f32::EPSILON {
+        // center outside of the box
+        return (distance < radius).then(|| offset / distance * (radius - distance));
+    }
+
+    // center inside of the box: leave through the nearest face
+    let to_min = center - min;
+    let to_max = max - center;
+    let faces = [
+        (to_min.x, vec3(-1., 0., 0.)),
+        (to_max.x, vec3(1., 0., 0.)),
+        (to_min.y, vec3(0., -1., 0.)),
+        (to_max.y, vec3(0., 1., 0.)),
+        (to_min.z, vec3(0., 0., -1.)),
+        (to_max.z, vec3(0., 0., 1.)),
+    ];
+    let (depth, normal) = faces
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+    Some(normal * (depth + radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_correction_vec() {
+        let a = Sphere::new(vec3(0., 0., 0.), 1.);
+        let b = Sphere::new(vec3(0., 0., 0.), 0.5);
+        let a_transform = Transform::from_translation(vec3(1., 0., 0.));
+        let b_transform = Transform::from_translation(vec3(0., 0., 0.));
+
+        let vec = Sphere::sphere_correction_vec(&a, &a_transform, &b, &b_transform).unwrap();
+        approx::assert_abs_diff_eq!(vec.x, 0.5, epsilon = 1e-5);
+        assert_eq!((vec.y, vec.z), (0., 0.));
+
+        let far = Transform::from_translation(vec3(2., 0., 0.));
+        assert!(Sphere::sphere_correction_vec(&a, &far, &b, &b_transform).is_none());
+    }
+
+    #[test]
+    fn test_sphere_aabb_correction_vec() {
+        let sphere = Sphere::new(vec3(0., 0., 0.), 0.5);
+        let aabb = AxisAlignedBoundingBox::new(vec3(-1., -1., -1.), vec3(1., 1., 1.));
+        let identity = Transform::identity();
+
+        // resting into the top face
+        let above = Transform::from_translation(vec3(0., 1.25, 0.));
+        let vec = Sphere::aabb_correction_vec(&sphere, &above, &aabb, &identity).unwrap();
+        approx::assert_abs_diff_eq!(vec.y, 0.25, epsilon = 1e-5);
+
+        // center inside, nearest to the +x face
+        let inside = Transform::from_translation(vec3(0.75, 0., 0.));
+        let vec = Sphere::aabb_correction_vec(&sphere, &inside, &aabb, &identity).unwrap();
+        approx::assert_abs_diff_eq!(vec.x, 0.75, epsilon = 1e-5);
+
+        // near a corner, but out of reach
+        let corner = Transform::from_translation(vec3(1.4, 1.4, 0.));
+        assert!(Sphere::aabb_correction_vec(&sphere, &corner, &aabb, &identity).is_none());
+    }
+}
@@ -0,0 +1,267 @@
+use cgmath::{InnerSpace, Matrix2, Matrix3, SquareMatrix};
+
+use crate::{
+    collision::{broadphase::Bounds, obb::BoxFrame},
+    transform::Transform,
+    ColliderShape, Vector3,
+};
+
+/// Gaps smaller than this count as contact
+const CONTACT_TOLERANCE: f32 = 1e-4;
+/// Steps toward a target, at most, before giving up and reporting the contact
+const MAX_ADVANCEMENT_STEPS: usize = 64;
+/// Refinements of the closest point of two pieces, at most
+const MAX_GJK_ITERATIONS: usize = 32;
+
+/// A convex piece of a shape in the world: the hull of its points, grown by a radius
+#[derive(Debug, Clone)]
+pub(crate) struct ConvexPiece {
+    points: Vec<Vector3>,
+    radius: f32,
+}
+
+impl ConvexPiece {
+    /// Point of the hull furthest along `direction`
+    fn support(&self, direction: Vector3) -> Vector3 {
+        self.points
+            .iter()
+            .copied()
+            .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+            .unwrap_or(Vector3::new(0., 0., 0.))
+    }
+}
+
+/// The convex pieces of the shape that may overlap `bounds`: the shape itself, or the
+/// triangles of a mesh
+pub(crate) fn convex_pieces(
+    shape: &ColliderShape,
+    transform: &Transform,
+    bounds: &Bounds,
+) -> Vec<ConvexPiece> {
+    let piece = |points: Vec<Vector3>, radius| ConvexPiece { points, radius };
+    match shape {
+        ColliderShape::AABB(aabb) => {
+            let (min, max) = aabb.world_bounds(transform);
+            vec![piece(BoxFrame::from_aabb(min, max).corners().to_vec(), 0.)]
+        }
+        ColliderShape::OBB(obb) => vec![piece(obb.world(transform).corners().to_vec(), 0.)],
+        ColliderShape::Sphere(sphere) => {
+            let (center, radius) = sphere.world(transform);
+            vec![piece(vec![center], radius)]
+        }
+        ColliderShape::Capsule(capsule) => {
+            let (start, end, radius) = capsule.world(transform);
+            vec![piece(vec![start, end], radius)]
+        }
+        ColliderShape::Mesh(mesh) => mesh
+            .world_triangles_near(transform, bounds)
+            .into_iter()
+            .map(|triangle| piece(triangle.to_vec(), 0.))
+            .collect(),
+    }
+}
+
+/// Distance `moving` travels along the unit `direction` before touching `target`, if it does
+/// within `max_distance`, with the unit normal of `target` facing it. The piece is advanced by
+/// the gap between them divided by how fast it closes, which can't go past the target
+/// however thin it is. Pieces overlapping from the start touch at 0, without a normal
+pub(crate) fn time_of_impact(
+    moving: &ConvexPiece,
+    target: &ConvexPiece,
+    direction: Vector3,
+    max_distance: f32,
+) -> Option<(f32, Option<Vector3>)> {
+    let mut distance = 0f32;
+    let mut normal = None;
+    for _ in 0..MAX_ADVANCEMENT_STEPS {
+        let Some((gap, across)) = separation(moving, direction * distance, target) else {
+            // overlapping from the start, or touching with rounding errors
+            return Some((distance, normal));
+        };
+        normal = Some(-across);
+        if gap <= CONTACT_TOLERANCE {
+            return Some((distance, normal));
+        }
+        // the pieces are on both sides of a plane across the gap: moving away from it, or
+        // along it, they never meet
+        let closing = direction.dot(across);
+        if closing <= f32::EPSILON {
+            return None;
+        }
+        // stopping within the tolerance, rather than on the target
+        distance += (gap - CONTACT_TOLERANCE / 2.) / closing;
+        if distance > max_distance {
+            return None;
+        }
+    }
+    // grazing the target, still closing in: stop before it
+    Some((distance, normal))
+}
+
+/// Gap between `a`, moved by `offset`, and `b`, with the unit direction from `a` to `b`
+/// across it. None if they overlap
+fn separation(a: &ConvexPiece, offset: Vector3, b: &ConvexPiece) -> Option<(f32, Vector3)> {
+    let closest = closest_difference(a, offset, b)?;
+    let length = closest.magnitude();
+    let gap = length - a.radius - b.radius;
+    (gap > 0.).then(|| (gap, -closest / length))
+}
+
+/// Point of the difference of the hulls, `a + offset - b`, closest to the origin (GJK).
+/// None if the hulls intersect
+fn closest_difference(a: &ConvexPiece, offset: Vector3, b: &ConvexPiece) -> Option<Vector3> {
+    let support = |direction: Vector3| a.support(direction) + offset - b.support(-direction);
+    let mut simplex = vec![support(Vector3::unit_x())];
+    let mut closest = simplex[0];
+    for _ in 0..MAX_GJK_ITERATIONS {
+        let length2 = closest.magnitude2();
+        if length2 < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        // no point of the difference is closer than the one found
+        let point = support(-closest);
+        if length2 - closest.dot(point) <= 1e-6 * length2 {
+            break;
+        }
+        simplex.push(point);
+        (closest, simplex) = closest_on_simplex(&simplex)?;
+    }
+    Some(closest)
+}
+
+/// Point of the simplex closest to the origin, with the smallest part of the simplex holding
+/// it. None if the simplex is a tetrahedron around the origin
+fn closest_on_simplex(simplex: &[Vector3]) -> Option<(Vector3, Vec<Vector3>)> {
+    let full = (1usize << simplex.len()) - 1;
+    let mut best: Option<(Vector3, usize)> = None;
+    for subset in 1..=full {
+        let points = (0..simplex.len())
+            .filter(|i| subset & (1 << i) != 0)
+            .map(|i| simplex[i])
+            .collect::<Vec<_>>();
+        let Some(point) = project_inside(&points) else {
+            continue;
+        };
+        if best.is_none_or(|(b, _)| point.magnitude2() < b.magnitude2()) {
+            best = Some((point, subset));
+        }
+    }
+    let (point, subset) = best?;
+    if simplex.len() == 4 && subset == full {
+        return None;
+    }
+    let kept = (0..simplex.len())
+        .filter(|i| subset & (1 << i) != 0)
+        .map(|i| simplex[i])
+        .collect();
+    Some((point, kept))
+}
+
+/// Point closest to the origin on the plane, line or space through the points, None if it's
+/// outside of their simplex or they are degenerate
+fn project_inside(points: &[Vector3]) -> Option<Vector3> {
+    // barycentric coordinates can be slightly negative on the edges
+    const EPS: f32 = -1e-6;
+    let origin = points[0];
+    let edges = points[1..].iter().map(|p| p - origin).collect::<Vec<_>>();
+    // weights of the edges, solving the normal equations of the projection
+    let weights = match edges[..] {
+        [] => vec![],
+        [e] => {
+            let length2 = e.magnitude2();
+            if length2 <= f32::EPSILON {
+                return None;
+            }
+            vec![-origin.dot(e) / length2]
+        }
+        [e1, e2] => {
+            let gram = Matrix2::new(e1.dot(e1), e1.dot(e2), e1.dot(e2), e2.dot(e2));
+            let w = gram.invert()? * cgmath::vec2(-origin.dot(e1), -origin.dot(e2));
+            vec![w.x, w.y]
+        }
+        [e1, e2, e3] => {
+            let w = Matrix3::from_cols(e1, e2, e3).invert()? * -origin;
+            vec![w.x, w.y, w.z]
+        }
+        _ => return None,
+    };
+    let inside = weights.iter().all(|w| *w >= EPS) && weights.iter().sum::<f32>() <= 1. - EPS;
+    inside.then(|| {
+        origin
+            + edges
+                .iter()
+                .zip(&weights)
+                .map(|(e, w)| e * *w)
+                .sum::<Vector3>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::*;
+
+    fn cube(center: Vector3, half: f32) -> ConvexPiece {
+        let reach = vec3(half, half, half);
+        ConvexPiece {
+            points: BoxFrame::from_aabb(center - reach, center + reach)
+                .corners()
+                .to_vec(),
+            radius: 0.,
+        }
+    }
+
+    fn ball(center: Vector3, radius: f32) -> ConvexPiece {
+        ConvexPiece {
+            points: vec![center],
+            radius,
+        }
+    }
+
+    #[test]
+    fn test_separation() {
+        let zero = vec3(0., 0., 0.);
+        let (gap, across) =
+            separation(&ball(zero, 0.5), zero, &cube(vec3(3., 3., 0.), 1.)).unwrap();
+        // closest to the edge of the cube at (2, 2)
+        approx::assert_abs_diff_eq!(gap, 8f32.sqrt() - 0.5, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(across.x, 0.5f32.sqrt(), epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(across.y, 0.5f32.sqrt(), epsilon = 1e-4);
+
+        let (gap, across) = separation(
+            &cube(zero, 1.),
+            vec3(0., 0., -1.),
+            &cube(vec3(0.5, 0.2, 2.), 0.5),
+        )
+        .unwrap();
+        approx::assert_abs_diff_eq!(gap, 1.5, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(across.z, 1., epsilon = 1e-4);
+
+        assert!(separation(&cube(zero, 1.), zero, &ball(vec3(1.2, 1.2, 0.), 0.5)).is_none());
+        assert!(separation(&cube(zero, 1.), zero, &cube(vec3(0.5, 0.5, 0.5), 1.)).is_none());
+    }
+
+    #[test]
+    fn test_time_of_impact() {
+        let zero = vec3(0., 0., 0.);
+        // a thin wall far away
+        let wall = ConvexPiece {
+            points: BoxFrame::from_aabb(vec3(999.99, -1., -1.), vec3(1000., 1., 1.))
+                .corners()
+                .to_vec(),
+            radius: 0.,
+        };
+        let (distance, normal) =
+            time_of_impact(&ball(zero, 0.01), &wall, Vector3::unit_x(), 2000.).unwrap();
+        approx::assert_abs_diff_eq!(distance, 999.98, epsilon = 1e-2);
+        approx::assert_abs_diff_eq!(normal.unwrap().x, -1., epsilon = 1e-4);
+
+        assert!(time_of_impact(&ball(zero, 0.01), &wall, Vector3::unit_x(), 900.).is_none());
+        assert!(time_of_impact(&ball(zero, 0.01), &wall, -Vector3::unit_x(), 2000.).is_none());
+        assert_eq!(
+            time_of_impact(&ball(zero, 1.), &cube(zero, 0.5), Vector3::unit_x(), 1.),
+            Some((0., None))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use crate::{collision::CollisionPhase, entity::EntityId};
+
+/// Another entity's collider entering, staying in, or leaving a trigger. The event is delivered
+/// to both entities
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerEvent {
+    /// Entity of the trigger collider
+    pub trigger: EntityId,
+    /// Entity whose collider overlaps the trigger
+    pub visitor: EntityId,
+    pub phase: CollisionPhase,
+}
+
+/// Entities inside of triggers, tracked across updates to know the phase of overlaps
+pub(crate) struct TriggerTracker {
+    // (trigger, visitor)
+    inside: HashSet<(EntityId, EntityId)>,
+    current: HashSet<(EntityId, EntityId)>,
+    events: Vec<(EntityId, TriggerEvent)>,
+}
+
+impl TriggerTracker {
+    pub fn new() -> Self {
+        Self {
+            inside: HashSet::new(),
+            current: HashSet::new(),
+            events: vec![],
+        }
+    }
+
+    /// Record that `visitor` overlaps a trigger of `trigger` during this pass
+    pub fn record(&mut self, trigger: EntityId, visitor: EntityId) {
+        self.current.insert((trigger, visitor));
+    }
+
+    /// Compare the overlaps of this pass to the last one, creating the events of the pass
+    pub fn finish_pass(&mut self) {
+        self.events.clear();
+        let current = std::mem::take(&mut self.current);
+        let mut changes = current
+            .iter()
+            .map(|pair| {
+                let phase = if self.inside.contains(pair) {
+                    CollisionPhase::Stay
+                } else {
+                    CollisionPhase::Enter
+                };
+                (*pair, phase)
+            })
+            .chain(
+                self.inside
+                    .difference(&current)
+                    .map(|pair| (*pair, CollisionPhase::Exit)),
+            )
+            .collect::<Vec<_>>();
+        // keep events in a stable order
+        changes.sort_by_key(|(pair, _)| *pair);
+        for ((trigger, visitor), phase) in changes {
+            let event = TriggerEvent {
+                trigger,
+                visitor,
+                phase,
+            };
+            self.events.push((trigger, event.clone()));
+            self.events.push((visitor, event));
+        }
+        self.inside = current;
+    }
+
+    /// Events of the last pass, with the entity receiving each of them
+    pub fn events(&self) -> &[(EntityId, TriggerEvent)] {
+        &self.events
+    }
+
+    /// Entities overlapping the triggers of `trigger` during the last pass
+    pub fn contents(&self, trigger: &EntityId) -> Vec<EntityId> {
+        let mut contents = self
+            .inside
+            .iter()
+            .filter(|(t, _)| t == trigger)
+            .map(|(_, visitor)| *visitor)
+            .collect::<Vec<_>>();
+        contents.sort();
+        contents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{
+        event::{OnEventContext, OnStartContext, OnTriggerContext, OnUpdateContext},
+        Collider, Component, Scene,
+    };
+
+    struct Recorder(Arc<Mutex<Vec<(EntityId, TriggerEvent)>>>);
+
+    impl Component for Recorder {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn on_trigger(&mut self, _scene: &mut Scene, context: OnTriggerContext) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((context.entity, context.trigger));
+        }
+    }
+
+    #[test]
+    fn test_trigger_enter_and_exit() {
+        let mut scene = Scene::new();
+        let zone = scene
+            .add_entity(scene.get_root(), "zone".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                zone,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(2., 2., 2.), false).with_trigger(true),
+            )
+            .unwrap();
+        let received = Arc::new(Mutex::new(vec![]));
+        scene
+            .add_component(zone, Recorder(received.clone()))
+            .unwrap();
+
+        let player = scene
+            .add_entity(scene.get_root(), "player".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&player)
+            .unwrap()
+            .set_position_global(vec3(1., 0., 0.));
+        scene
+            .add_collider(player, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+
+        // triggers also detect static colliders
+        let wall = scene
+            .add_entity(scene.get_root(), "wall".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&wall)
+            .unwrap()
+            .set_position_global(vec3(0., 2.5, 0.));
+        scene
+            .add_collider(
+                wall,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(1., 1., 1.), false),
+            )
+            .unwrap();
+
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        // the trigger doesn't push the player out
+        let position = scene.get_mut_transform(&player).unwrap().position_global();
+        assert_eq!(position, vec3(1., 0., 0.));
+        assert_eq!(scene.trigger_contents(&zone), vec![player, wall]);
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                (
+                    zone,
+                    TriggerEvent {
+                        trigger: zone,
+                        visitor: player,
+                        phase: CollisionPhase::Enter,
+                    }
+                ),
+                (
+                    zone,
+                    TriggerEvent {
+                        trigger: zone,
+                        visitor: wall,
+                        phase: CollisionPhase::Enter,
+                    }
+                )
+            ]
+        );
+        // the player's side of the event
+        assert!(scene
+            .trigger_events()
+            .iter()
+            .any(|(entity, e)| *entity == player && e.trigger == zone));
+
+        received.lock().unwrap().clear();
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        let phases = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, e)| e.phase)
+            .collect::<Vec<_>>();
+        assert_eq!(phases, vec![CollisionPhase::Stay, CollisionPhase::Stay]);
+
+        received.lock().unwrap().clear();
+        scene
+            .get_mut_transform(&player)
+            .unwrap()
+            .set_position_global(vec3(5., 0., 0.));
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        let phases = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, e)| (e.visitor, e.phase))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases,
+            vec![(player, CollisionPhase::Exit), (wall, CollisionPhase::Stay)]
+        );
+        assert_eq!(scene.trigger_contents(&zone), vec![wall]);
+    }
+}
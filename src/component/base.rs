@@ -1,22 +1,54 @@
 use std::{
     any::{self, Any},
-    sync::TryLockResult,
+    sync::{atomic::AtomicU64, TryLockResult},
 };
 
-use crate::event::{OnEventContext, OnStartContext, OnUpdateContext};
-use crate::{component::ComponentId, error::*};
+use crate::event::{
+    OnCollisionContext, OnEventContext, OnStartContext, OnTriggerContext, OnUpdateContext,
+};
+use crate::{
+    component::{ComponentAccess, ComponentId, ParallelView},
+    error::*,
+};
 
 use super::super::scene::Scene;
 
+static NEXT_COMPONENT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
 pub trait Component: Any + Send + Sync + 'static {
     fn on_start(&mut self, scene: &mut Scene, context: OnStartContext);
     fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext);
     fn on_event(&mut self, scene: &mut Scene, context: OnEventContext);
+
+    /// Opt into parallel updates by declaring the other component types read and written by
+    /// `on_parallel_update`. Components of this type then get `on_parallel_update` calls
+    /// from worker threads instead of `on_update` calls.
+    fn access() -> Option<ComponentAccess>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Update for components that declared their `access`. Only the declared component types
+    /// can be reached through `view`.
+    fn on_parallel_update(&mut self, _view: &mut ParallelView, _context: OnUpdateContext) {}
+
+    /// Called on every component of both entities when their colliders enter, stay in, or exit
+    /// contact. Collisions are delivered after colliders are pushed apart, before `on_update`.
+    fn on_collision(&mut self, _scene: &mut Scene, _context: OnCollisionContext) {}
+
+    /// Called on every component of a trigger's entity and of the entity overlapping it, when
+    /// the overlap begins, goes on, or ends. Delivered right after collisions.
+    fn on_trigger(&mut self, _scene: &mut Scene, _context: OnTriggerContext) {}
 }
 
 pub struct DynComponentRef {
     type_id: any::TypeId,
     id: ComponentId,
+    // creation order, which is the order components are updated in
+    sequence: u64,
+    access: Option<ComponentAccess>,
     inner: Box<dyn Component>,
 }
 
@@ -24,8 +56,16 @@ impl DynComponentRef {
     pub fn new<C: Component>(component: C) -> Self {
         let type_id = any::TypeId::of::<C>();
         let id = ComponentId::new();
+        let sequence = NEXT_COMPONENT_SEQUENCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let access = C::access().map(|a| a.write_type(type_id));
         let inner = Box::new(component);
-        Self { type_id, id, inner }
+        Self {
+            type_id,
+            id,
+            sequence,
+            access,
+            inner,
+        }
     }
 
     pub fn try_on_start(
@@ -55,6 +95,33 @@ impl DynComponentRef {
         Ok(())
     }
 
+    pub fn try_on_collision(
+        &mut self,
+        scene: &mut Scene,
+        context: OnCollisionContext,
+    ) -> TryLockResult<()> {
+        self.inner.on_collision(scene, context);
+        Ok(())
+    }
+
+    pub fn try_on_trigger(
+        &mut self,
+        scene: &mut Scene,
+        context: OnTriggerContext,
+    ) -> TryLockResult<()> {
+        self.inner.on_trigger(scene, context);
+        Ok(())
+    }
+
+    pub fn try_on_parallel_update(
+        &mut self,
+        view: &mut ParallelView,
+        context: OnUpdateContext,
+    ) -> TryLockResult<()> {
+        self.inner.on_parallel_update(view, context);
+        Ok(())
+    }
+
     pub fn downcast_mut<C: Component>(&mut self) -> Result<&mut C> {
         let is_type_match = self.type_id == any::TypeId::of::<C>();
         if is_type_match {
@@ -83,6 +150,19 @@ impl DynComponentRef {
         &*(self.inner.as_ref() as *const dyn Component as *const C)
     }
 
+    pub fn type_id(&self) -> any::TypeId {
+        self.type_id
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The declared access of components updated in parallel, including their own type
+    pub fn access(&self) -> Option<&ComponentAccess> {
+        self.access.as_ref()
+    }
+
     pub fn id(&self) -> ComponentId {
         self.id.clone()
     }
@@ -1,8 +1,10 @@
 /// Module for components
 mod base;
 mod id;
+mod parallel;
 mod store;
 
 pub use base::*;
 pub use id::*;
+pub use parallel::*;
 pub use store::*;
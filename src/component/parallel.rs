@@ -0,0 +1,423 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::{
+    component::{ComponentId, DynComponentRef},
+    entity::EntityId,
+    scene::Scene,
+    Component,
+};
+
+/// The component types a component reads and writes while updating in parallel.
+/// A component always has write access to components of its own type.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentAccess {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+}
+
+impl ComponentAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare read access to components of type `C`
+    pub fn read<C: Component>(mut self) -> Self {
+        self.reads.push(TypeId::of::<C>());
+        self
+    }
+
+    /// Declare write access to components of type `C`
+    pub fn write<C: Component>(self) -> Self {
+        self.write_type(TypeId::of::<C>())
+    }
+
+    pub(crate) fn write_type(mut self, type_id: TypeId) -> Self {
+        if !self.writes.contains(&type_id) {
+            self.writes.push(type_id);
+        }
+        self
+    }
+
+    pub fn can_read(&self, type_id: &TypeId) -> bool {
+        self.reads.contains(type_id) || self.writes.contains(type_id)
+    }
+
+    pub fn can_write(&self, type_id: &TypeId) -> bool {
+        self.writes.contains(type_id)
+    }
+
+    pub(crate) fn writes(&self) -> &[TypeId] {
+        &self.writes
+    }
+
+    /// Two accesses conflict if one writes a type that the other reads or writes
+    pub fn conflicts(&self, other: &ComponentAccess) -> bool {
+        self.writes.iter().any(|t| other.can_read(t))
+            || other.writes.iter().any(|t| self.can_read(t))
+    }
+}
+
+/// Components of the same type, updated one after the other on the same thread
+pub(crate) struct ParallelGroup {
+    pub access: ComponentAccess,
+    pub components: Vec<(ComponentId, EntityId)>,
+}
+
+/// A step of the update of the components, in the order they were added
+#[derive(Debug, PartialEq)]
+pub(crate) enum UpdateStep {
+    /// Update the component on the event-loop thread
+    Serial(usize),
+    /// Update the groups of components on worker threads at the same time, the components of
+    /// each group one after the other
+    Parallel(Vec<Vec<usize>>),
+}
+
+/// Split components, given in update order with their type and declared access, into steps.
+/// Consecutive components that declared their access are grouped by type into a parallel step,
+/// until one conflicts with the group of another type: groups in a step don't conflict, and
+/// each keeps its components in order, so the steps give the same result as updating the
+/// components one after the other
+pub(crate) fn schedule_updates(
+    components: &[Option<(TypeId, &ComponentAccess)>],
+) -> Vec<UpdateStep> {
+    let mut steps = vec![];
+    // the groups of the parallel step being gathered
+    let mut groups: Vec<(TypeId, &ComponentAccess, Vec<usize>)> = vec![];
+    for (idx, component) in components.iter().enumerate() {
+        let Some((type_id, access)) = component else {
+            if !groups.is_empty() {
+                steps.push(UpdateStep::Parallel(
+                    groups.drain(..).map(|g| g.2).collect(),
+                ));
+            }
+            steps.push(UpdateStep::Serial(idx));
+            continue;
+        };
+        let conflicts = groups
+            .iter()
+            .any(|(other, other_access, _)| other != type_id && other_access.conflicts(access));
+        if conflicts {
+            steps.push(UpdateStep::Parallel(
+                groups.drain(..).map(|g| g.2).collect(),
+            ));
+        }
+        match groups.iter_mut().find(|(other, ..)| other == type_id) {
+            Some(group) => group.2.push(idx),
+            None => groups.push((*type_id, *access, vec![idx])),
+        }
+    }
+    if !groups.is_empty() {
+        steps.push(UpdateStep::Parallel(
+            groups.drain(..).map(|g| g.2).collect(),
+        ));
+    }
+    steps
+}
+
+/// Access to the scene for components updating in parallel.
+/// Lookups of component types that were not declared in the component's access return None.
+pub struct ParallelView<'a> {
+    scene: &'a Scene,
+    // components of the types written by this group, taken out of the scene
+    owned: &'a mut HashMap<ComponentId, DynComponentRef>,
+    access: &'a ComponentAccess,
+}
+
+impl<'a> ParallelView<'a> {
+    pub(crate) fn new(
+        scene: &'a Scene,
+        owned: &'a mut HashMap<ComponentId, DynComponentRef>,
+        access: &'a ComponentAccess,
+    ) -> Self {
+        Self {
+            scene,
+            owned,
+            access,
+        }
+    }
+
+    pub fn get_ref_component<C: Component>(&self, id: &ComponentId) -> Option<&C> {
+        let type_id = TypeId::of::<C>();
+        if self.access.can_write(&type_id) {
+            self.owned.get(id)?.downcast_ref().ok()
+        } else if self.access.can_read(&type_id) {
+            self.scene.get_ref_component(id)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut_component<C: Component>(&mut self, id: &ComponentId) -> Option<&mut C> {
+        if self.access.can_write(&TypeId::of::<C>()) {
+            self.owned.get_mut(id)?.downcast_mut().ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn get_first_component_id_from_entity<C: Component>(
+        &self,
+        entity: &EntityId,
+    ) -> Option<ComponentId> {
+        let entity = self.scene.get_entity(entity)?;
+        entity
+            .components
+            .iter()
+            .find(|c| self.get_ref_component::<C>(c).is_some())
+            .cloned()
+    }
+
+    pub fn get_component_entity(&self, comp_id: &ComponentId) -> Option<EntityId> {
+        self.scene.get_component_entity(comp_id)
+    }
+
+    pub fn parent(&self, child_id: &EntityId) -> Option<EntityId> {
+        self.scene.parent(child_id)
+    }
+
+    pub fn children(&self, parent_id: &EntityId) -> Vec<EntityId> {
+        self.scene.children(parent_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{OnEventContext, OnStartContext, OnUpdateContext};
+
+    struct Velocity(f32);
+    struct Position(f32);
+    struct Doubler;
+
+    impl Component for Velocity {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+    }
+
+    impl Component for Position {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn access() -> Option<ComponentAccess> {
+            Some(ComponentAccess::new().read::<Velocity>())
+        }
+
+        fn on_parallel_update(&mut self, view: &mut ParallelView, context: OnUpdateContext) {
+            let velocity = view
+                .get_first_component_id_from_entity::<Velocity>(&context.entity)
+                .unwrap();
+            self.0 += view.get_ref_component::<Velocity>(&velocity).unwrap().0;
+        }
+    }
+
+    impl Component for Doubler {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn access() -> Option<ComponentAccess> {
+            Some(ComponentAccess::new().write::<Velocity>())
+        }
+
+        fn on_parallel_update(&mut self, view: &mut ParallelView, context: OnUpdateContext) {
+            let velocity = view
+                .get_first_component_id_from_entity::<Velocity>(&context.entity)
+                .unwrap();
+            view.get_mut_component::<Velocity>(&velocity).unwrap().0 *= 2.;
+        }
+    }
+
+    struct Value(f32);
+    struct Source(f32);
+    /// Adds the source to the value, in parallel if `P`
+    struct Adder<const P: bool>;
+    /// Triples the value, in parallel if `P`
+    struct Scaler<const P: bool>;
+    /// Sets the source from the value, always serially
+    struct Feeder;
+
+    macro_rules! data_component {
+        ($t:ty) => {
+            impl Component for $t {
+                fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+                fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+                fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+            }
+        };
+    }
+    data_component!(Value);
+    data_component!(Source);
+
+    impl<const P: bool> Component for Adder<P> {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+            let source = scene.get_first_component_id_from_entity::<Source>(&context.entity);
+            let source = scene
+                .get_ref_component::<Source>(&source.unwrap())
+                .unwrap()
+                .0;
+            let value = scene.get_first_component_id_from_entity::<Value>(&context.entity);
+            scene.get_mut_component::<Value>(&value.unwrap()).unwrap().0 += source;
+        }
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn access() -> Option<ComponentAccess> {
+            P.then(|| ComponentAccess::new().read::<Source>().write::<Value>())
+        }
+
+        fn on_parallel_update(&mut self, view: &mut ParallelView, context: OnUpdateContext) {
+            let source = view.get_first_component_id_from_entity::<Source>(&context.entity);
+            let source = view
+                .get_ref_component::<Source>(&source.unwrap())
+                .unwrap()
+                .0;
+            let value = view.get_first_component_id_from_entity::<Value>(&context.entity);
+            view.get_mut_component::<Value>(&value.unwrap()).unwrap().0 += source;
+        }
+    }
+
+    impl<const P: bool> Component for Scaler<P> {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+            let value = scene.get_first_component_id_from_entity::<Value>(&context.entity);
+            scene.get_mut_component::<Value>(&value.unwrap()).unwrap().0 *= 3.;
+        }
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+
+        fn access() -> Option<ComponentAccess> {
+            P.then(|| ComponentAccess::new().write::<Value>())
+        }
+
+        fn on_parallel_update(&mut self, view: &mut ParallelView, context: OnUpdateContext) {
+            let value = view.get_first_component_id_from_entity::<Value>(&context.entity);
+            view.get_mut_component::<Value>(&value.unwrap()).unwrap().0 *= 3.;
+        }
+    }
+
+    impl Component for Feeder {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+            let value = scene.get_first_component_id_from_entity::<Value>(&context.entity);
+            let value = scene.get_ref_component::<Value>(&value.unwrap()).unwrap().0;
+            let source = scene.get_first_component_id_from_entity::<Source>(&context.entity);
+            scene
+                .get_mut_component::<Source>(&source.unwrap())
+                .unwrap()
+                .0 = value + 1.;
+        }
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+    }
+
+    /// The values after a few updates, with adders and scalers in parallel if `P`
+    fn interleaved_values<const P: bool>() -> Vec<f32> {
+        let mut scene = Scene::new();
+        let entities = (0..4)
+            .map(|i| {
+                let entity = scene.add_entity(scene.get_root(), format!("{i}")).unwrap();
+                scene.add_component(entity, Value(i as f32)).unwrap();
+                scene.add_component(entity, Source(1.)).unwrap();
+                entity
+            })
+            .collect::<Vec<_>>();
+        // parallel runs are cut by the serial feeders
+        for entity in &entities {
+            scene.add_component(*entity, Adder::<P>).unwrap();
+        }
+        for entity in &entities {
+            scene.add_component(*entity, Feeder).unwrap();
+        }
+        for entity in &entities {
+            scene.add_component(*entity, Scaler::<P>).unwrap();
+            scene.add_component(*entity, Adder::<P>).unwrap();
+        }
+
+        for _ in 0..3 {
+            scene.on_update(Duration::from_millis(10)).unwrap();
+        }
+        entities
+            .iter()
+            .map(|entity| {
+                let value = scene.get_first_component_id_from_entity::<Value>(entity);
+                scene.get_ref_component::<Value>(&value.unwrap()).unwrap().0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interleaved_parallel_and_serial_updates() {
+        assert_eq!(interleaved_values::<true>(), interleaved_values::<false>());
+    }
+
+    #[test]
+    fn test_schedule_updates() {
+        let read = ComponentAccess::new().read::<Velocity>();
+        let write = ComponentAccess::new().write::<Velocity>();
+        let other = ComponentAccess::new().write::<Position>();
+        let (a, b, c) = (
+            TypeId::of::<Position>(),
+            TypeId::of::<Doubler>(),
+            TypeId::of::<u8>(),
+        );
+
+        let steps = schedule_updates(&[
+            Some((a, &read)),
+            Some((c, &other)),
+            Some((a, &read)),
+            // conflicts with the readers
+            Some((b, &write)),
+            Some((b, &write)),
+            None,
+            Some((a, &read)),
+        ]);
+        assert_eq!(
+            steps,
+            vec![
+                UpdateStep::Parallel(vec![vec![0, 2], vec![1]]),
+                UpdateStep::Parallel(vec![vec![3, 4]]),
+                UpdateStep::Serial(5),
+                UpdateStep::Parallel(vec![vec![6]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parallel_update_matches_serial_order() {
+        let mut scene = Scene::new();
+        let mut positions = vec![];
+        for i in 0..8 {
+            let entity = scene.add_entity(scene.get_root(), format!("{i}")).unwrap();
+            scene.add_component(entity, Velocity(i as f32)).unwrap();
+            positions.push(scene.add_component(entity, Position(0.)).unwrap());
+            scene.add_component(entity, Doubler).unwrap();
+        }
+
+        // positions read the velocities before the doublers write them
+        scene.on_update(Duration::from_millis(10)).unwrap();
+        scene.on_update(Duration::from_millis(10)).unwrap();
+
+        for (i, position) in positions.iter().enumerate() {
+            let position = scene.get_ref_component::<Position>(position).unwrap();
+            assert_eq!(position.0, 3. * i as f32);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::*;
+use std::any::TypeId;
 use std::collections::HashMap;
 
 use crate::{
@@ -44,11 +45,37 @@ impl ComponentStore {
         }
     }
 
-    pub fn get_mut_disjoint_2<C1: Component, C2: Component>(&mut self, ids: [&ComponentId; 2]) -> (Option<&mut C1>, Option<&mut C2>) {
+    pub fn get_dyn(&self, id: &ComponentId) -> Option<&DynComponentRef> {
+        self.components.get(id)
+    }
+
+    /// Take every component of the given types out of the store
+    pub fn take_types(&mut self, types: &[TypeId]) -> HashMap<ComponentId, DynComponentRef> {
+        let ids = self
+            .components
+            .iter()
+            .filter(|(_, c)| types.contains(&c.type_id()))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .filter_map(|id| self.components.remove_entry(&id))
+            .collect()
+    }
+
+    /// Put components taken with `take_types` back in the store
+    pub fn put_back(&mut self, components: HashMap<ComponentId, DynComponentRef>) {
+        self.components.extend(components);
+    }
+
+    /// Borrow two different components mutably at once
+    pub fn get_mut_disjoint_2<C1: Component, C2: Component>(&mut self, ids: [&ComponentId; 2]) -> Result<(&mut C1, &mut C2)> {
+        if ids[0] == ids[1] {
+            return Err(SceneError::SameComponentTwice(ids[0].clone()).into());
+        }
         let [c1, c2] = self.components.get_disjoint_mut(ids);
-        let c1 = c1.map(|x| (*x).downcast_mut::<C1>().unwrap());
-        let c2 = c2.map(|x| (*x).downcast_mut::<C2>().unwrap());
-        (c1, c2)
+        let c1 = c1.ok_or(SceneError::ComponentNotFound(ids[0].clone()))?;
+        let c2 = c2.ok_or(SceneError::ComponentNotFound(ids[1].clone()))?;
+        Ok((c1.downcast_mut::<C1>()?, c2.downcast_mut::<C2>()?))
     }
 
     pub fn get_mut_first<C: Component>(&mut self) -> Option<&mut C> {
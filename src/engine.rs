@@ -111,18 +111,18 @@ impl Engine {
         *s = scene;
     }
 
-    pub fn on_start(&self) {
+    pub fn on_start(&self) -> Result<()> {
         let scene_ref = &mut self.scene.write().unwrap();
-        scene_ref.on_start();
+        scene_ref.on_start()
     }
 
-    pub fn on_update(&self, delta_time: Duration) {
+    pub fn on_update(&self, delta_time: Duration) -> Result<()> {
         let scene_ref = &mut self.scene.write().unwrap();
-        scene_ref.on_update(delta_time);
+        scene_ref.on_update(delta_time)
     }
 
-    pub fn on_event(&self, event: &WindowEvent) {
+    pub fn on_event(&self, event: &WindowEvent) -> Result<()> {
         let scene_ref = &mut self.scene.write().unwrap();
-        scene_ref.on_event(event);
+        scene_ref.on_event(event)
     }
 }
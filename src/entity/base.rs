@@ -4,7 +4,7 @@ use crate::component::ComponentId;
 
 static NEXT_ENTITY_ID: AtomicU32 = AtomicU32::new(0);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct EntityId {
     id: u32,
 }
@@ -19,7 +19,6 @@ impl EntityId {
 
 #[derive(Debug, Clone)]
 pub(crate) struct Entity {
-    #[allow(unused)]
     pub name: String,
     pub components: Vec<ComponentId>,
 }
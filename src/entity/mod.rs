@@ -1,4 +1,5 @@
 mod base;
+pub mod observer;
 /// Module for entities and the entity graph
 pub mod transform;
 
@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use std::sync::atomic::AtomicU32;
+
+use crate::{entity::EntityId, scene::Scene, transform::Transform};
+
+static NEXT_OBSERVER_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Closure run when an entity's global transform changes, with the new global transform
+pub type TransformObserver = Box<dyn FnMut(&mut Scene, EntityId, Transform) + Send + Sync>;
+
+/// Handle to a transform observer, used to remove it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverHandle {
+    id: u32,
+}
+
+impl ObserverHandle {
+    fn new() -> Self {
+        let id = NEXT_OBSERVER_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self { id }
+    }
+}
+
+struct Observer {
+    handle: ObserverHandle,
+    entity: EntityId,
+    // None while the callback is running
+    callback: Option<TransformObserver>,
+}
+
+/// Keeps track of the callbacks subscribed to transform changes
+pub(crate) struct TransformObservers {
+    observers: Vec<Observer>,
+}
+
+impl TransformObservers {
+    pub fn new() -> Self {
+        Self { observers: vec![] }
+    }
+
+    pub fn add(&mut self, entity: EntityId, callback: TransformObserver) -> ObserverHandle {
+        let handle = ObserverHandle::new();
+        self.observers.push(Observer {
+            handle,
+            entity,
+            callback: Some(callback),
+        });
+        handle
+    }
+
+    pub fn remove(&mut self, handle: ObserverHandle) -> bool {
+        let len = self.observers.len();
+        self.observers.retain(|o| o.handle != handle);
+        self.observers.len() != len
+    }
+
+    /// Remove every observer of the entity
+    pub fn remove_entity(&mut self, entity: &EntityId) {
+        self.observers.retain(|o| o.entity != *entity);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Take out the callbacks observing any of the entities. They must be given back with
+    /// `restore`
+    pub fn take(
+        &mut self,
+        entities: &HashSet<EntityId>,
+    ) -> Vec<(ObserverHandle, EntityId, TransformObserver)> {
+        self.observers
+            .iter_mut()
+            .filter(|o| entities.contains(&o.entity))
+            .filter_map(|o| Some((o.handle, o.entity, o.callback.take()?)))
+            .collect()
+    }
+
+    /// Give a callback back once it has run, unless it was removed in the meantime
+    pub fn restore(&mut self, handle: ObserverHandle, callback: TransformObserver) {
+        if let Some(observer) = self.observers.iter_mut().find(|o| o.handle == handle) {
+            observer.callback = Some(callback);
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{transform::Transform, Component, Vector3};
+use cgmath::{ElementWise as _, InnerSpace as _, Matrix3, Rotation as _};
+
+use crate::{transform::Transform, Component, Quaternion, Vector3};
 
 /// Component that represents a transform in the entity hierarchy.
 #[derive(Debug, Clone)]
@@ -11,7 +13,8 @@ pub struct TransformComponent {
     global: Transform,
     // when global transform changes on a frame, this flag is raised to indicate a need to
     // update anything that depended on this transform. this allows components that depend on
-    // the transform to know when it has changed. Cleared every on_update.
+    // the transform to know when it has changed. Cleared at the end of every on_update, once
+    // the change has been propagated (see `Scene::changed_transforms`).
     dirty: bool,
 }
 
@@ -26,11 +29,17 @@ impl TransformComponent {
         }
     }
 
-    #[allow(unused)]
     pub fn local_ref(&self) -> &Transform {
         &self.local
     }
 
+    /// Replace the local transform: T_local = T
+    pub fn set_local(&mut self, local: Transform) {
+        self.local = local;
+
+        self.update_global();
+    }
+
     /// Translate along global axis
     /// where dT is the global translation
     pub fn translate_global(&mut self, vec: Vector3) {
@@ -41,19 +50,203 @@ impl TransformComponent {
     }
 
     /// Translate along this local axis:
-    /// T_local = T_local * dT
-    /// where dT is the local translation
+    /// T_local = dT * T_local
+    /// where dT is the local translation, rotated by the local rotation.
+    /// The local scale doesn't affect the distance moved.
     pub fn translate_local(&mut self, vec: Vector3) {
-        self.local = self.local * Transform::from_translation(vec);
+        let vec = self.local.rotation_matrix() * vec;
+        self.local = Transform::from_translation(vec) * self.local;
 
         self.update_global();
     }
 
+    /// Rotate around the local axis, keeping the local scale along the same axis
     pub fn rotate_euler_local(&mut self, euler: Vector3) {
-        self.local = self.local
-            * Transform::from_angle_z(euler.z)
+        let rotation = Transform::from_angle_z(euler.z)
             * Transform::from_angle_y(euler.y)
             * Transform::from_angle_x(euler.x);
+        self.local = Transform::from_parts(
+            self.local.translation(),
+            self.local.rotation_matrix() * rotation.rotation_matrix(),
+            self.local.scale(),
+        );
+
+        self.update_global();
+    }
+
+    /// Position relative to the parent
+    pub fn position_local(&self) -> Vector3 {
+        self.local.translation()
+    }
+
+    /// Position in the world
+    pub fn position_global(&self) -> Vector3 {
+        self.global.translation()
+    }
+
+    pub fn set_position_local(&mut self, position: Vector3) {
+        self.local = self.local.with_translation(position);
+
+        self.update_global();
+    }
+
+    /// Move to a position in the world
+    pub fn set_position_global(&mut self, position: Vector3) {
+        let position = self.parent.inverse() * position;
+        self.set_position_local(position);
+    }
+
+    /// Rotation relative to the parent
+    pub fn rotation_local(&self) -> Quaternion {
+        self.local.rotation()
+    }
+
+    /// Rotation relative to the world
+    pub fn rotation_global(&self) -> Quaternion {
+        self.global.rotation()
+    }
+
+    pub fn set_rotation_local(&mut self, rotation: Quaternion) {
+        self.local = self.local.with_rotation(rotation);
+
+        self.update_global();
+    }
+
+    /// Set the local rotation so that the global rotation is `rotation`.
+    /// Like `scale_global`, only exact when no parent is skewed
+    pub fn set_rotation_global(&mut self, rotation: Quaternion) {
+        let rotation = self.parent.rotation().invert() * rotation;
+        self.set_rotation_local(rotation);
+    }
+
+    /// Rotate around the local axis: R_local = R_local * dR
+    pub fn rotate_local(&mut self, rotation: Quaternion) {
+        self.set_rotation_local(self.rotation_local() * rotation);
+    }
+
+    /// Rotate around the global axis, around the entity's position: R_global = dR * R_global
+    pub fn rotate_global(&mut self, rotation: Quaternion) {
+        self.set_rotation_global(rotation * self.rotation_global());
+    }
+
+    /// Rotate around a point in the world, moving the entity and turning it
+    pub fn rotate_around(&mut self, point: Vector3, rotation: Quaternion) {
+        let position = point + rotation.rotate_vector(self.position_global() - point);
+        self.rotate_global(rotation);
+        self.set_position_global(position);
+    }
+
+    /// Turn the entity so that its forward direction (-z) points to a point in the world,
+    /// keeping its up direction (+y) as close to `up` as possible.
+    /// Does nothing if the target is at the entity's position.
+    pub fn look_at(&mut self, target: Vector3, up: Vector3) {
+        let forward = target - self.position_global();
+        if forward.magnitude2() <= f32::EPSILON {
+            return;
+        }
+        let forward = forward.normalize();
+
+        let mut right = forward.cross(up);
+        if right.magnitude2() <= f32::EPSILON {
+            // looking along `up`: any perpendicular up will do
+            let fallback = if forward.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_z()
+            };
+            right = fallback.cross(forward);
+        }
+        let right = right.normalize();
+        let up = right.cross(forward);
+
+        let rotation = Quaternion::from(Matrix3::from_cols(right, up, -forward));
+        self.set_rotation_global(rotation);
+    }
+
+    /// Convert a point from this entity's space to world space
+    pub fn point_to_world(&self, point: Vector3) -> Vector3 {
+        self.global.transform_point(point)
+    }
+
+    /// Convert a point from world space to this entity's space
+    pub fn point_from_world(&self, point: Vector3) -> Vector3 {
+        self.global.inverse_transform_point(point)
+    }
+
+    /// Convert a point from this entity's space to its parent's space
+    pub fn point_to_parent(&self, point: Vector3) -> Vector3 {
+        self.local.transform_point(point)
+    }
+
+    /// Convert a point from the parent's space to this entity's space
+    pub fn point_from_parent(&self, point: Vector3) -> Vector3 {
+        self.local.inverse_transform_point(point)
+    }
+
+    /// Convert a direction from this entity's space to world space, see
+    /// `Transform::transform_direction`
+    pub fn direction_to_world(&self, direction: Vector3) -> Vector3 {
+        self.global.transform_direction(direction)
+    }
+
+    /// Convert a direction from world space to this entity's space
+    pub fn direction_from_world(&self, direction: Vector3) -> Vector3 {
+        self.global.inverse_transform_direction(direction)
+    }
+
+    /// Convert a direction from this entity's space to its parent's space
+    pub fn direction_to_parent(&self, direction: Vector3) -> Vector3 {
+        self.local.transform_direction(direction)
+    }
+
+    /// Convert a direction from the parent's space to this entity's space
+    pub fn direction_from_parent(&self, direction: Vector3) -> Vector3 {
+        self.local.inverse_transform_direction(direction)
+    }
+
+    /// Direction the entity faces in the world: its -z axis
+    pub fn forward(&self) -> Vector3 {
+        self.global.rotation_matrix() * -Vector3::unit_z()
+    }
+
+    /// Direction to the right of the entity in the world: its +x axis
+    pub fn right(&self) -> Vector3 {
+        self.global.rotation_matrix() * Vector3::unit_x()
+    }
+
+    /// Direction above the entity in the world: its +y axis
+    pub fn up(&self) -> Vector3 {
+        self.global.rotation_matrix() * Vector3::unit_y()
+    }
+
+    /// Scale relative to the parent: the local scale
+    pub fn scale_local(&self) -> Vector3 {
+        self.local.scale()
+    }
+
+    /// Scale relative to the world.
+    /// Only exact when no parent has a non-uniform scale and a rotation at the same time,
+    /// as that skews the children
+    pub fn scale_global(&self) -> Vector3 {
+        self.global.scale()
+    }
+
+    /// Set the scale relative to the parent. Scales too close to 0 are clamped to `MIN_SCALE`
+    pub fn set_scale_local(&mut self, scale: Vector3) {
+        self.local = self.local.with_scale(scale);
+
+        self.update_global();
+    }
+
+    /// Set the local scale so that the global scale is `scale`, see `scale_global`
+    pub fn set_scale_global(&mut self, scale: Vector3) {
+        let parent = self.parent.scale();
+        self.set_scale_local(scale.div_element_wise(parent));
+    }
+
+    /// Multiply the local scale along each local axis, so the global scale is multiplied too
+    pub fn multiply_scale(&mut self, factor: Vector3) {
+        self.local = self.local.with_scale_multiplied(factor);
 
         self.update_global();
     }
@@ -96,3 +289,85 @@ impl Default for TransformComponent {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, Rotation3 as _};
+
+    use super::*;
+
+    fn assert_vec_eq(a: Vector3, b: Vector3) {
+        assert!((a - b).magnitude() < 1e-4, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_global_setters_under_parent() {
+        let mut parent = TransformComponent::new();
+        parent.set_position_global(vec3(1., 0., 0.));
+        parent.rotate_global(Quaternion::from_angle_y(Deg(90.)));
+
+        let mut child = TransformComponent::new();
+        child.set_parent(parent.global());
+        child.set_position_global(vec3(0., 2., 3.));
+        assert_vec_eq(child.position_global(), vec3(0., 2., 3.));
+
+        child.look_at(vec3(0., 2., -7.), Vector3::unit_y());
+        assert_vec_eq(child.forward(), -Vector3::unit_z());
+        assert_vec_eq(child.up(), Vector3::unit_y());
+        assert_vec_eq(child.right(), Vector3::unit_x());
+
+        child.rotate_around(vec3(0., 2., 0.), Quaternion::from_angle_y(Deg(90.)));
+        assert_vec_eq(child.position_global(), vec3(3., 2., 0.));
+        assert_vec_eq(child.forward(), -Vector3::unit_x());
+        assert!(child.is_dirty());
+    }
+
+    #[test]
+    fn test_space_conversions() {
+        let mut parent = TransformComponent::new();
+        parent.set_position_global(vec3(0., 0., 5.));
+        parent.set_scale_local(vec3(2., 2., 2.));
+
+        let mut child = TransformComponent::new();
+        child.set_parent(parent.global());
+        child.set_position_local(vec3(1., 0., 0.));
+        child.rotate_local(Quaternion::from_angle_z(Deg(90.)));
+
+        let world = child.point_to_world(vec3(1., 0., 0.));
+        assert_vec_eq(world, vec3(2., 2., 5.));
+        assert_vec_eq(child.point_from_world(world), vec3(1., 0., 0.));
+        assert_vec_eq(child.point_to_parent(vec3(1., 0., 0.)), vec3(1., 1., 0.));
+        assert_vec_eq(child.point_from_parent(vec3(1., 1., 0.)), vec3(1., 0., 0.));
+
+        // directions ignore translation
+        assert_vec_eq(
+            child.direction_to_world(Vector3::unit_x()),
+            vec3(0., 2., 0.),
+        );
+        assert_vec_eq(
+            child.direction_from_world(vec3(0., 2., 0.)),
+            Vector3::unit_x(),
+        );
+        assert_vec_eq(
+            child.direction_to_parent(Vector3::unit_x()),
+            Vector3::unit_y(),
+        );
+        assert_vec_eq(
+            child.direction_from_parent(Vector3::unit_y()),
+            Vector3::unit_x(),
+        );
+    }
+
+    #[test]
+    fn test_zero_scale() {
+        let mut parent = TransformComponent::new();
+        parent.set_scale_local(vec3(0., 1., 1.));
+
+        let mut child = TransformComponent::new();
+        child.set_parent(parent.global());
+        child.set_scale_global(vec3(1., 0., 1.));
+        child.set_position_global(vec3(0., 1., 2.));
+        assert_vec_eq(child.position_global(), vec3(0., 1., 2.));
+        assert_vec_eq(child.point_from_world(vec3(0., 1., 2.)), vec3(0., 0., 0.));
+    }
+}
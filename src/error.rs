@@ -1,6 +1,8 @@
 #![allow(unused)]
 use std::fmt;
 
+use crate::{component::ComponentId, entity::EntityId};
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
@@ -9,9 +11,56 @@ pub enum Error {
     IoError(std::io::Error),
     ObjLoadError(tobj::LoadError),
     ComponentDowncastError,
+    RonError(ron::Error),
+    /// A component type that was not registered was found in a scene file
+    UnregisteredComponent(String),
+    SceneError(SceneError),
+    /// An animator was asked to play a clip it doesn't have
+    AnimationNotFound(String),
+    /// No collision layer has this name
+    LayerNotFound(String),
+    /// Collision layers go from 0 to 31
+    InvalidLayer(u8),
     Other(String),
 }
 
+/// Inconsistencies in the scene's entity tree and components
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneError {
+    EntityNotFound(EntityId),
+    ComponentNotFound(ComponentId),
+    /// A component was found twice in the store
+    DuplicateComponent(ComponentId),
+    /// The same component was asked for twice where different ones were expected
+    SameComponentTwice(ComponentId),
+    /// A hook of the component couldn't lock what it needed
+    ComponentLocked(ComponentId),
+    /// Every entity must have exactly one transform
+    MissingTransform(EntityId),
+    DuplicateTransform(EntityId),
+    /// The component is listed by an entity, but is mapped to another entity (or none)
+    ComponentEntityMismatch {
+        component: ComponentId,
+        entity: EntityId,
+    },
+    /// The component is mapped to an entity, but the entity doesn't list it
+    OrphanComponent(ComponentId),
+    /// The child's parent doesn't exist or doesn't list it as a child
+    BrokenParentLink { child: EntityId, parent: EntityId },
+    /// The entity can't be reached from the root
+    UnreachableEntity(EntityId),
+    /// The root entity can't be removed
+    RootRemoval,
+    /// The transform of an entity can't be removed, only the entity itself
+    TransformRemoval(EntityId),
+}
+
+impl From<SceneError> for Error {
+    fn from(e: SceneError) -> Self {
+        Self::SceneError(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{self:?}")
@@ -37,6 +86,18 @@ impl From<image::ImageError> for Error {
     }
 }
 
+impl From<ron::Error> for Error {
+    fn from(e: ron::Error) -> Self {
+        Self::RonError(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for Error {
+    fn from(e: ron::error::SpannedError) -> Self {
+        Self::RonError(e.into())
+    }
+}
+
 impl From<tobj::LoadError> for Error {
     fn from(e: tobj::LoadError) -> Self {
         Self::ObjLoadError(e)
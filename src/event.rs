@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use crate::{component::ComponentId, entity::EntityId};
+use crate::{component::ComponentId, entity::EntityId, CollisionEvent, TriggerEvent};
 
 pub struct OnStartContext {
     /// Context: current caller's information
@@ -25,6 +25,24 @@ pub struct OnEventContext {
     pub event: WindowEvent,
 }
 
+pub struct OnCollisionContext {
+    /// Context: current caller's information
+    pub entity: EntityId,
+    pub component: ComponentId,
+
+    /// Collision of the caller's entity with another one
+    pub collision: CollisionEvent,
+}
+
+pub struct OnTriggerContext {
+    /// Context: current caller's information
+    pub entity: EntityId,
+    pub component: ComponentId,
+
+    /// Overlap of a trigger, either the caller's entity's or one the caller's entity is in
+    pub trigger: TriggerEvent,
+}
+
 pub enum WindowEvent {
     KeyboardInput { event: KeyEvent },
     CursorMoved { position_x: f32, position_y: f32 },
@@ -32,7 +32,9 @@ impl ApplicationHandler for EngineHandler {
         let engine = get_engine();
         let mut engine = engine.write().unwrap();
         engine.set_window(window);
-        engine.on_start();
+        if let Err(e) = engine.on_start() {
+            eprintln!("Scene start failed: {e}");
+        }
     }
 
     fn window_event(
@@ -59,7 +61,9 @@ impl ApplicationHandler for EngineHandler {
                     renderer.try_render().unwrap();
                 }
                 _ => {
-                    engine.on_event(&event);
+                    if let Err(e) = engine.on_event(&event) {
+                        eprintln!("Scene event failed: {e}");
+                    }
                 }
             }
         }
@@ -72,7 +76,9 @@ impl ApplicationHandler for EngineHandler {
         if update_dt_measure >= self.update_dt {
             let engine = get_engine();
             let engine = engine.read().unwrap();
-            engine.on_update(update_dt_measure);
+            if let Err(e) = engine.on_update(update_dt_measure) {
+                eprintln!("Scene update failed: {e}");
+            }
             self.last_update = now;
         }
 
@@ -1,3 +1,4 @@
+mod animation;
 mod camera;
 mod collision;
 mod component;
@@ -7,22 +8,38 @@ mod error;
 mod event;
 mod handler;
 mod model;
+mod physics;
 mod render;
 mod resources;
 mod scene;
+mod serialization;
 mod texture;
+mod timer;
 mod transform;
+mod tween;
 mod types;
 
+pub use animation::{
+    AnimationClip, AnimationEvent, AnimationEventCallback, AnimationValue, Animator,
+    Interpolation, Keyframe, Track, TrackTarget,
+};
 pub use camera::Camera;
 pub use collision::*;
-pub use component::Component;
+pub use component::{Component, ComponentAccess, ComponentId, ParallelView};
 pub use engine::{get_engine, Engine, EngineConfig};
+pub use entity::observer::{ObserverHandle, TransformObserver};
 pub use entity::transform::TransformComponent;
 pub use entity::EntityId;
 pub use error::*;
 pub use event::*;
 pub use model::{Material, Mesh, Model, Vertex};
-pub use resources::{load_image, load_model};
+pub use physics::{CharacterController, RigidBody};
+pub use resources::{
+    load_animation, load_collision_mesh, load_image, load_model, load_scene, save_scene,
+};
 pub use scene::Scene;
+pub use serialization::ComponentRegistry;
+pub use timer::{TimerCallback, TimerHandle};
+pub use transform::{Transform, MIN_SCALE};
+pub use tween::{Easing, Repeat, Tween, TweenCallback, TweenHandle, TweenOptions, Tweener};
 pub use types::*;
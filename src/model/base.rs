@@ -14,6 +14,8 @@ use super::super::{
 pub struct Model {
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
+    /// The file the model was loaded from, if any. Needed to save the model in a scene file
+    pub source: Option<String>,
 }
 
 impl Component for Model {
@@ -22,13 +22,17 @@ pub struct MeshBuffers {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub instance_buffer: wgpu::Buffer,
+    /// What was last written to the instance buffer
+    pub instance: TransformRaw,
 }
 
 /// Used for representing each instance (it's transform) in the shader
 #[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct TransformRaw {
     model: [[f32; 4]; 4],
+    // inverse transpose of the model's 3x3 part, so normals stay perpendicular under scale
+    normal: [[f32; 3]; 3],
 }
 
 impl Mesh {
@@ -43,6 +47,15 @@ impl Mesh {
         }
     }
 
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Indices of the vertices, three per triangle
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
     pub fn set_vertices(&mut self, vertices: Vec<Vertex>) {
         self.vertices = vertices;
         // deletes buffers
@@ -62,6 +75,10 @@ impl Mesh {
         return self.buffers.as_ref();
     }
 
+    pub fn buffers_mut(&mut self) -> Option<&mut MeshBuffers> {
+        self.buffers.as_mut()
+    }
+
     /// Gets the bounding box of the mesh
     pub fn aabb_ref(&mut self) -> &AxisAlignedBoundingBox {
         if self.bounding_box.is_some() {
@@ -91,9 +108,10 @@ impl Mesh {
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
 
+        let instance = Transform::identity().to_raw();
         let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&[Transform::identity().to_raw()]), // only one instance
+            contents: bytemuck::cast_slice(&[instance]), // only one instance
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -101,6 +119,7 @@ impl Mesh {
             vertex_buffer,
             index_buffer,
             instance_buffer,
+            instance,
         });
     }
 
@@ -188,6 +207,22 @@ impl TransformRaw {
                     shader_location: 8,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // the normal matrix is a mat3, taking 3 more slots
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 19]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
@@ -197,6 +232,7 @@ impl Transform {
     pub fn to_raw(self) -> TransformRaw {
         TransformRaw {
             model: self.as_matrix().into(),
+            normal: self.normal_matrix().into(),
         }
     }
 }
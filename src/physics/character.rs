@@ -0,0 +1,526 @@
+use std::f32::consts::FRAC_PI_4;
+
+use cgmath::{InnerSpace, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    event::{OnEventContext, OnStartContext, OnUpdateContext},
+    transform::Transform,
+    ColliderShape, Component, QueryFilter, Ray, RayHit, RigidBody, Scene, Vector3,
+};
+
+/// Gap kept between the character and what it touches, so that its casts start outside of
+/// colliders
+const SKIN: f32 = 0.01;
+/// Times a move slides along what it hits, at most
+const MAX_SLIDES: usize = 4;
+
+/// Moves its entity by casting a shape through the colliders of the scene: the character
+/// walks and slides along what it hits, climbs slopes up to `max_slope` and steps up to
+/// `step_height`, falls, jumps, and pushes the rigid bodies in its way.
+///
+/// The shape is the controller's own, the colliders of the entity are ignored. Give the entity
+/// a dynamic collider and a kinematic `RigidBody` for other bodies to collide with it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterController {
+    pub shape: ColliderShape,
+    /// Velocity the character walks at, set by the game. Only its part across the up
+    /// direction, opposite to gravity, is used
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub walk_velocity: Vector3,
+    /// Steepest walkable slope, in radians. Steeper ones block the character like walls
+    pub max_slope: f32,
+    /// Highest ledge the character walks onto without jumping
+    pub step_height: f32,
+    /// Speed the character leaves the ground at when jumping
+    pub jump_speed: f32,
+    /// Multiplier of the scene's gravity
+    pub gravity_scale: f32,
+    /// Force applied to the rigid bodies the character walks into
+    pub push_force: f32,
+    /// Bit mask of the collider layers blocking the character
+    pub layers: u32,
+    // speed along the up direction
+    #[serde(skip)]
+    vertical_speed: f32,
+    #[serde(skip)]
+    jump_requested: bool,
+    #[serde(skip)]
+    ground_normal: Option<Vector3>,
+}
+
+impl CharacterController {
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            walk_velocity: Vector3::zero(),
+            max_slope: FRAC_PI_4,
+            step_height: 0.3,
+            jump_speed: 5.,
+            gravity_scale: 1.,
+            push_force: 20.,
+            layers: u32::MAX,
+            vertical_speed: 0.,
+            jump_requested: false,
+            ground_normal: None,
+        }
+    }
+
+    /// Jump during the next update, if the character is on the ground by then
+    pub fn jump(&mut self) {
+        self.jump_requested = true;
+    }
+
+    /// Whether the character stood on walkable ground at the end of the last update
+    pub fn is_grounded(&self) -> bool {
+        self.ground_normal.is_some()
+    }
+
+    /// Unit normal of the ground under the character, None when it's in the air
+    pub fn ground_normal(&self) -> Option<Vector3> {
+        self.ground_normal
+    }
+
+    /// Speed along the up direction, positive when jumping and negative when falling
+    pub fn vertical_speed(&self) -> f32 {
+        self.vertical_speed
+    }
+
+    fn move_character(&mut self, scene: &mut Scene, context: &OnUpdateContext) {
+        let delta_time = context.delta_time.as_secs_f32();
+        let Ok(start) = scene.global_transform(&context.entity) else {
+            return;
+        };
+        let gravity = scene.gravity() * self.gravity_scale;
+        let up = if gravity.magnitude2() > f32::EPSILON {
+            -gravity.normalize()
+        } else {
+            Vector3::unit_y()
+        };
+        let sweep = Sweep {
+            scene,
+            shape: &self.shape,
+            start,
+            filter: QueryFilter::new()
+                .with_layers(self.layers)
+                .excluding(context.entity),
+            up,
+            min_ground_dot: self.max_slope.cos(),
+        };
+
+        // gravity and jumps
+        let grounded = self.ground_normal.is_some();
+        if grounded && self.jump_requested {
+            self.vertical_speed = self.jump_speed;
+        } else if grounded && self.vertical_speed <= 0. {
+            // standing still on slopes instead of sliding down them
+            self.vertical_speed = 0.;
+        } else {
+            self.vertical_speed -= gravity.magnitude() * delta_time;
+        }
+        self.jump_requested = false;
+        let on_ground = grounded && self.vertical_speed <= 0.;
+
+        // walk along the ground, or across the up direction in the air
+        let mut walk = self.walk_velocity - up * self.walk_velocity.dot(up);
+        if let (true, Some(normal)) = (on_ground, self.ground_normal) {
+            let speed = walk.magnitude();
+            let along = walk - normal * walk.dot(normal);
+            if along.magnitude2() > f32::EPSILON {
+                walk = along.normalize() * speed;
+            }
+        }
+        let walk = walk * delta_time;
+        let mut walk_hits = vec![];
+        let start_position = start.translation();
+        let mut position = sweep.slide(start_position, walk, true, &mut walk_hits);
+        let blocked = walk_hits.iter().any(|hit| !sweep.walkable(hit.normal));
+        if on_ground && blocked && self.step_height > 0. {
+            let mut step_hits = vec![];
+            if let Some(stepped) =
+                sweep.step_up(start_position, walk, self.step_height, &mut step_hits)
+            {
+                if sweep.across(stepped - start_position)
+                    > sweep.across(position - start_position) + 1e-4
+                {
+                    position = stepped;
+                    walk_hits = step_hits;
+                }
+            }
+        }
+
+        // fall, or rise
+        let before = position;
+        position = sweep.slide(
+            position,
+            up * self.vertical_speed * delta_time,
+            false,
+            &mut vec![],
+        );
+        if delta_time > 0. {
+            let moved = (position - before).dot(up) / delta_time;
+            self.vertical_speed = if self.vertical_speed > 0. {
+                self.vertical_speed.min(moved.max(0.))
+            } else {
+                self.vertical_speed.max(moved.min(0.))
+            };
+        }
+
+        // follow the ground down slopes and stairs
+        if on_ground {
+            if let Some(hit) = sweep.cast(position, -up, self.step_height + SKIN) {
+                if sweep.walkable(sweep.surface_under(&hit)) {
+                    position -= up * (hit.distance - SKIN).max(0.);
+                }
+            }
+        }
+        self.ground_normal = sweep
+            .cast(position, -up, 2. * SKIN)
+            .map(|hit| sweep.surface_under(&hit))
+            .filter(|normal| sweep.walkable(*normal) && self.vertical_speed <= 0.);
+        if self.ground_normal.is_some() {
+            self.vertical_speed = 0.;
+        }
+
+        self.push_bodies(scene, &walk_hits, up);
+        if let Ok(transform) = scene.get_mut_transform(&context.entity) {
+            transform.set_position_global(position);
+        }
+    }
+
+    /// Push the rigid bodies hit while walking away from the character
+    fn push_bodies(&self, scene: &mut Scene, hits: &[RayHit], up: Vector3) {
+        let mut pushed = vec![];
+        for hit in hits {
+            if pushed.contains(&hit.entity) {
+                continue;
+            }
+            pushed.push(hit.entity);
+            let direction = -(hit.normal - up * hit.normal.dot(up));
+            if direction.magnitude2() <= f32::EPSILON {
+                continue;
+            }
+            let Some(body) = scene.get_first_component_id_from_entity::<RigidBody>(&hit.entity)
+            else {
+                continue;
+            };
+            if let Some(body) = scene.get_mut_component::<RigidBody>(&body) {
+                if body.inverse_mass() > 0. {
+                    body.apply_force(direction.normalize() * self.push_force);
+                }
+            }
+        }
+    }
+}
+
+/// Casts of the character's shape, from anywhere it moves to during an update
+struct Sweep<'a> {
+    scene: &'a Scene,
+    shape: &'a ColliderShape,
+    // global transform at the start of the update
+    start: Transform,
+    filter: QueryFilter,
+    up: Vector3,
+    // cosine of the steepest walkable slope
+    min_ground_dot: f32,
+}
+
+impl Sweep<'_> {
+    fn cast(&self, position: Vector3, direction: Vector3, distance: f32) -> Option<RayHit> {
+        let transform =
+            Transform::from_translation(position - self.start.translation()) * self.start;
+        self.scene.shape_cast_from_outside(
+            self.shape,
+            &transform,
+            direction,
+            distance,
+            &self.filter,
+        )
+    }
+
+    /// Normal of the surface under the point hit. Rounded shapes touch the edges of steps at
+    /// an angle, while the top of the steps is flat
+    fn surface_under(&self, hit: &RayHit) -> Vector3 {
+        let across = hit.normal - self.up * hit.normal.dot(self.up);
+        let inwards = if across.magnitude2() > f32::EPSILON {
+            -across.normalize() * SKIN
+        } else {
+            Vector3::zero()
+        };
+        let ray = Ray::new(hit.point + self.up * 2. * SKIN + inwards, -self.up);
+        self.scene
+            .raycast(&ray, 3. * SKIN, &self.filter)
+            // a ray starting inside of the collider is on a steep slope
+            .filter(|surface| surface.distance > 0.)
+            .map_or(hit.normal, |surface| surface.normal)
+    }
+
+    fn walkable(&self, normal: Vector3) -> bool {
+        normal.dot(self.up) >= self.min_ground_dot
+    }
+
+    /// Length of the part of `motion` across the up direction
+    fn across(&self, motion: Vector3) -> f32 {
+        (motion - self.up * motion.dot(self.up)).magnitude()
+    }
+
+    /// Move from `position` by `motion`, sliding along what's hit. When `walking`, slopes too
+    /// steep to walk on block like walls instead of being climbed. Returns where the move ends
+    fn slide(
+        &self,
+        mut position: Vector3,
+        mut motion: Vector3,
+        walking: bool,
+        hits: &mut Vec<RayHit>,
+    ) -> Vector3 {
+        for _ in 0..MAX_SLIDES {
+            let distance = motion.magnitude();
+            if distance < 1e-6 {
+                break;
+            }
+            let direction = motion / distance;
+            let Some(hit) = self.cast(position, direction, distance + SKIN) else {
+                position += motion;
+                break;
+            };
+            let travel = (hit.distance - SKIN).max(0.);
+            position += direction * travel;
+            motion -= direction * travel;
+
+            let mut normal = hit.normal;
+            if walking && !self.walkable(normal) {
+                let across = normal - self.up * normal.dot(self.up);
+                if across.magnitude2() > f32::EPSILON {
+                    normal = across.normalize();
+                }
+            }
+            // keep the part of the move along the surface
+            let into = motion.dot(normal);
+            if into < 0. {
+                motion -= normal * into;
+            }
+            hits.push(hit);
+        }
+        position
+    }
+
+    /// Walk by `walk` after rising by up to `height`, then come down on walkable ground.
+    /// None if there's no ground to step onto
+    fn step_up(
+        &self,
+        position: Vector3,
+        walk: Vector3,
+        height: f32,
+        hits: &mut Vec<RayHit>,
+    ) -> Option<Vector3> {
+        let rise = self
+            .cast(position, self.up, height + SKIN)
+            .map_or(height, |hit| (hit.distance - SKIN).max(0.));
+        let moved = self.slide(position + self.up * rise, walk, true, hits);
+        let ground = self.cast(moved, -self.up, rise + SKIN)?;
+        if !self.walkable(self.surface_under(&ground)) {
+            return None;
+        }
+        Some(moved - self.up * (ground.distance - SKIN).max(0.))
+    }
+}
+
+impl Component for CharacterController {
+    fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+    fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+        self.move_character(scene, &context);
+    }
+
+    fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::{vec3, Deg, Rotation3};
+
+    use super::*;
+    use crate::{entity::EntityId, Capsule, Collider, ComponentId, Quaternion};
+
+    fn add_static(scene: &mut Scene, position: Vector3, collider: Collider) -> EntityId {
+        let entity = scene
+            .add_entity(scene.get_root(), "static".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&entity)
+            .unwrap()
+            .set_position_global(position);
+        scene.add_collider(entity, collider).unwrap();
+        entity
+    }
+
+    /// A floor with its top at 0, and a character of height 2 standing on it at the origin
+    fn scene_with_character() -> (Scene, EntityId, ComponentId) {
+        let mut scene = Scene::new();
+        add_static(
+            &mut scene,
+            vec3(0., -1., 0.),
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(50., 1., 50.), false),
+        );
+        let character = scene
+            .add_entity(scene.get_root(), "character".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&character)
+            .unwrap()
+            .set_position_global(vec3(0., 1. + SKIN, 0.));
+        let shape =
+            ColliderShape::Capsule(Capsule::new(vec3(0., -0.5, 0.), vec3(0., 0.5, 0.), 0.5));
+        let controller = scene
+            .add_component(character, CharacterController::new(shape))
+            .unwrap();
+        (scene, character, controller)
+    }
+
+    fn walk(scene: &mut Scene, controller: &ComponentId, velocity: Vector3, seconds: f32) {
+        scene
+            .get_mut_component::<CharacterController>(controller)
+            .unwrap()
+            .walk_velocity = velocity;
+        for _ in 0..(seconds * 50.).round() as usize {
+            scene.on_update(Duration::from_millis(20)).unwrap();
+        }
+    }
+
+    fn position(scene: &mut Scene, entity: &EntityId) -> Vector3 {
+        scene.get_mut_transform(entity).unwrap().position_global()
+    }
+
+    #[test]
+    fn test_walk_and_slide() {
+        let (mut scene, character, controller) = scene_with_character();
+        add_static(
+            &mut scene,
+            vec3(3., 1., 0.),
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 2., 50.), false),
+        );
+
+        // walking diagonally into the wall slides along it
+        walk(&mut scene, &controller, vec3(4., 0., 4.), 1.);
+        let at = position(&mut scene, &character);
+        approx::assert_abs_diff_eq!(at.x, 2. - SKIN, epsilon = 0.01);
+        approx::assert_abs_diff_eq!(at.y, 1. + SKIN, epsilon = 0.01);
+        approx::assert_abs_diff_eq!(at.z, 4., epsilon = 0.1);
+        let controller = scene
+            .get_ref_component::<CharacterController>(&controller)
+            .unwrap();
+        assert!(controller.is_grounded());
+        approx::assert_abs_diff_eq!(controller.ground_normal().unwrap().y, 1., epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_steps_and_slopes() {
+        // a low step is walked onto and off
+        let (mut scene, character, controller) = scene_with_character();
+        add_static(
+            &mut scene,
+            vec3(2., 0.1, 0.),
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 0.1, 50.), false),
+        );
+        walk(&mut scene, &controller, vec3(2., 0., 0.), 1.);
+        // slowed down a little while rolling over the edge
+        let at = position(&mut scene, &character);
+        approx::assert_abs_diff_eq!(at.x, 2., epsilon = 0.1);
+        approx::assert_abs_diff_eq!(at.y, 1.2 + SKIN, epsilon = 0.02);
+        walk(&mut scene, &controller, vec3(2., 0., 0.), 1.);
+        let at = position(&mut scene, &character);
+        approx::assert_abs_diff_eq!(at.x, 4., epsilon = 0.1);
+        approx::assert_abs_diff_eq!(at.y, 1. + SKIN, epsilon = 0.02);
+
+        // a high one blocks
+        let (mut scene, character, controller) = scene_with_character();
+        add_static(
+            &mut scene,
+            vec3(2., 0.25, 0.),
+            Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 0.25, 50.), false),
+        );
+        walk(&mut scene, &controller, vec3(2., 0., 0.), 1.);
+        let at = position(&mut scene, &character);
+        approx::assert_abs_diff_eq!(at.x, 1. - SKIN, epsilon = 0.01);
+        approx::assert_abs_diff_eq!(at.y, 1. + SKIN, epsilon = 0.01);
+
+        // ramps rising along x, starting around x = 3
+        for (angle, climbed) in [(30., true), (60., false)] {
+            let (mut scene, character, controller) = scene_with_character();
+            let ramp = add_static(
+                &mut scene,
+                vec3(4., 0., 0.),
+                Collider::new_obb(vec3(0., 0., 0.), vec3(3., 0.5, 50.), false),
+            );
+            scene
+                .get_mut_transform(&ramp)
+                .unwrap()
+                .set_rotation_global(Quaternion::from_angle_z(Deg(angle)));
+            walk(&mut scene, &controller, vec3(2., 0., 0.), 2.5);
+            let at = position(&mut scene, &character);
+            assert_eq!(at.y > 1.5, climbed, "{angle} degrees ramp, at {at:?}");
+            let controller = scene
+                .get_ref_component::<CharacterController>(&controller)
+                .unwrap();
+            assert!(controller.is_grounded());
+        }
+    }
+
+    #[test]
+    fn test_jump_and_push() {
+        let (mut scene, character, controller) = scene_with_character();
+        walk(&mut scene, &controller, vec3(0., 0., 0.), 0.1);
+        scene
+            .get_mut_component::<CharacterController>(&controller)
+            .unwrap()
+            .jump();
+        walk(&mut scene, &controller, vec3(0., 0., 0.), 0.2);
+        let jumping = scene
+            .get_ref_component::<CharacterController>(&controller)
+            .unwrap();
+        assert!(!jumping.is_grounded());
+        assert!(jumping.vertical_speed() > 0.);
+        assert!(position(&mut scene, &character).y > 1.5);
+        // back on the ground
+        walk(&mut scene, &controller, vec3(0., 0., 0.), 1.);
+        assert!(scene
+            .get_ref_component::<CharacterController>(&controller)
+            .unwrap()
+            .is_grounded());
+        approx::assert_abs_diff_eq!(
+            position(&mut scene, &character).y,
+            1. + SKIN,
+            epsilon = 0.01
+        );
+
+        // walking into a crate pushes it
+        let crate_entity = scene
+            .add_entity(scene.get_root(), "crate".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&crate_entity)
+            .unwrap()
+            .set_position_global(vec3(1.5, 0.5, 0.));
+        scene
+            .add_collider(
+                crate_entity,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 0.5, 0.5), true),
+            )
+            .unwrap();
+        let body = scene
+            .add_component(crate_entity, RigidBody::new(1.))
+            .unwrap();
+        walk(&mut scene, &controller, vec3(2., 0., 0.), 1.);
+        assert!(position(&mut scene, &crate_entity).x > 2.);
+        assert!(
+            scene
+                .get_ref_component::<RigidBody>(&body)
+                .unwrap()
+                .velocity
+                .x
+                > 0.
+        );
+    }
+}
@@ -0,0 +1,9 @@
+/// Module for rigid body dynamics: velocities integrated every update, and contacts resolved
+/// with impulses. Also home of the character controller, moved by casts instead of forces
+mod character;
+mod rigid_body;
+mod solver;
+
+pub use character::*;
+pub use rigid_body::*;
+pub(crate) use solver::{contact_impulse, stack_levels, ContactBody, VELOCITY_ITERATIONS};
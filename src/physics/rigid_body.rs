@@ -0,0 +1,311 @@
+use cgmath::{InnerSpace, Rad, Rotation3, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::transform::TransformComponent,
+    event::{OnEventContext, OnStartContext, OnUpdateContext},
+    physics::solver::ContactBody,
+    scene::Scene,
+    Component, Quaternion, Vector3,
+};
+
+/// A body moved by the physics step: its velocity is integrated every update, before colliders
+/// are pushed apart, and its collisions bounce and slow it down. The entity needs a dynamic
+/// collider to collide.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RigidBody {
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub velocity: Vector3,
+    /// Axis of rotation in the world, scaled by the speed in radians per second
+    #[serde(with = "crate::types::serde_array::vector3")]
+    pub angular_velocity: Vector3,
+    /// Bodies of zero or infinite mass are immovable: neither gravity, forces nor collisions
+    /// change their velocity
+    pub mass: f32,
+    /// Kinematic bodies only move by the velocity they are given: gravity, forces and
+    /// collisions leave them alone, and they push other colliders without being pushed back
+    #[serde(default)]
+    pub kinematic: bool,
+    /// Multiplier of the scene's gravity
+    pub gravity_scale: f32,
+    /// How fast the velocity decays, per second
+    pub drag: f32,
+    /// How fast the angular velocity decays, per second
+    pub angular_drag: f32,
+    /// Bounciness, from 0 (no bounce) to 1 (no speed lost)
+    pub restitution: f32,
+    /// Friction coefficient: the largest sideways impulse of a contact, relative to the
+    /// impulse pushing the bodies apart
+    pub friction: f32,
+    // sum of the forces applied until the next step
+    #[serde(skip, default = "Vector3::zero")]
+    force: Vector3,
+}
+
+impl RigidBody {
+    pub fn new(mass: f32) -> Self {
+        Self {
+            velocity: Vector3::zero(),
+            angular_velocity: Vector3::zero(),
+            mass,
+            kinematic: false,
+            gravity_scale: 1.,
+            drag: 0.,
+            angular_drag: 0.,
+            restitution: 0.,
+            friction: 0.5,
+            force: Vector3::zero(),
+        }
+    }
+
+    /// A kinematic body, moving at `velocity`
+    pub fn new_kinematic(velocity: Vector3) -> Self {
+        Self {
+            velocity,
+            kinematic: true,
+            ..Self::new(1.)
+        }
+    }
+
+    /// Zero for immovable bodies: kinematic ones, and those of zero or infinite mass
+    pub fn inverse_mass(&self) -> f32 {
+        if !self.kinematic && self.mass > 0. && self.mass.is_finite() {
+            1. / self.mass
+        } else {
+            0.
+        }
+    }
+
+    /// Apply a force during the next physics step
+    pub fn apply_force(&mut self, force: Vector3) {
+        self.force += force;
+    }
+
+    /// Change the velocity at once, by `impulse` divided by the mass
+    pub fn apply_impulse(&mut self, impulse: Vector3) {
+        self.velocity += impulse * self.inverse_mass();
+    }
+
+    pub(crate) fn contact_body(&self) -> ContactBody {
+        ContactBody {
+            velocity: self.velocity,
+            inverse_mass: self.inverse_mass(),
+            restitution: Some(self.restitution),
+            friction: Some(self.friction),
+        }
+    }
+
+    /// Accelerate the body, then move its transform by its velocity
+    pub(crate) fn integrate(
+        &mut self,
+        gravity: Vector3,
+        delta_time: f32,
+        transform: &mut TransformComponent,
+    ) {
+        if self.inverse_mass() > 0. {
+            let acceleration = gravity * self.gravity_scale + self.force * self.inverse_mass();
+            self.velocity += acceleration * delta_time;
+        }
+        self.force = Vector3::zero();
+        self.velocity /= 1. + self.drag * delta_time;
+        self.angular_velocity /= 1. + self.angular_drag * delta_time;
+
+        transform.translate_global(self.velocity * delta_time);
+        let speed = self.angular_velocity.magnitude();
+        if speed > f32::EPSILON {
+            let axis = self.angular_velocity / speed;
+            let rotation = Quaternion::from_axis_angle(axis, Rad(speed * delta_time));
+            transform.rotate_global(rotation);
+        }
+    }
+}
+
+impl Component for RigidBody {
+    fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+    fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+    fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{collision::CollisionPhase, entity::EntityId, Collider, ComponentId};
+
+    fn scene_with_floor() -> Scene {
+        let mut scene = Scene::new();
+        let floor = scene
+            .add_entity(scene.get_root(), "floor".to_string())
+            .unwrap();
+        scene
+            .add_collider(
+                floor,
+                Collider::new_aabb(vec3(0., -1., 0.), vec3(50., 1., 50.), false),
+            )
+            .unwrap();
+        scene
+    }
+
+    fn add_ball(scene: &mut Scene, position: Vector3, body: RigidBody) -> (EntityId, ComponentId) {
+        let ball = scene
+            .add_entity(scene.get_root(), "ball".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&ball)
+            .unwrap()
+            .set_position_global(position);
+        scene
+            .add_collider(ball, Collider::new_sphere(vec3(0., 0., 0.), 0.5, true))
+            .unwrap();
+        let body = scene.add_component(ball, body).unwrap();
+        (ball, body)
+    }
+
+    #[test]
+    fn test_free_fall() {
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., -10., 0.));
+        let (ball, body) = add_ball(&mut scene, vec3(0., 0., 0.), RigidBody::new(2.));
+        for _ in 0..10 {
+            scene.on_update(Duration::from_millis(100)).unwrap();
+        }
+        let velocity = scene
+            .get_ref_component::<RigidBody>(&body)
+            .unwrap()
+            .velocity;
+        approx::assert_abs_diff_eq!(velocity.y, -10., epsilon = 1e-4);
+        // each step moves by the velocity at its end
+        let position = scene.get_mut_transform(&ball).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.y, -5.5, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_bounce_and_slide() {
+        let mut scene = scene_with_floor();
+        let mut body = RigidBody::new(1.);
+        body.velocity = vec3(0., -5., 0.);
+        body.restitution = 0.5;
+        let (ball, body) = add_ball(&mut scene, vec3(0., 0.55, 0.), body);
+
+        scene.on_update(Duration::from_millis(20)).unwrap();
+        // falling at 5.2 when hitting the floor
+        let velocity = scene
+            .get_ref_component::<RigidBody>(&body)
+            .unwrap()
+            .velocity;
+        approx::assert_abs_diff_eq!(velocity.y, 2.6, epsilon = 0.01);
+        let position = scene.get_mut_transform(&ball).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.y, 0.5, epsilon = 1e-4);
+
+        // resting on the floor and sliding: friction slows the ball down by g * friction
+        let mut scene = scene_with_floor();
+        let mut body = RigidBody::new(1.);
+        body.velocity = vec3(2., 0., 0.);
+        let (ball, body) = add_ball(&mut scene, vec3(0., 0.5, 0.), body);
+        for _ in 0..10 {
+            scene.on_update(Duration::from_millis(20)).unwrap();
+        }
+        let velocity = scene
+            .get_ref_component::<RigidBody>(&body)
+            .unwrap()
+            .velocity;
+        approx::assert_abs_diff_eq!(velocity.x, 2. - 0.5 * 9.81 * 0.2, epsilon = 0.01);
+        approx::assert_abs_diff_eq!(velocity.y, 0., epsilon = 1e-4);
+        let position = scene.get_mut_transform(&ball).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.y, 0.5, epsilon = 1e-4);
+    }
+
+    fn add_box(scene: &mut Scene, position: Vector3, body: RigidBody) -> (EntityId, ComponentId) {
+        let entity = scene
+            .add_entity(scene.get_root(), "box".to_string())
+            .unwrap();
+        scene
+            .get_mut_transform(&entity)
+            .unwrap()
+            .set_position_global(position);
+        scene
+            .add_collider(
+                entity,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(0.5, 0.5, 0.5), true),
+            )
+            .unwrap();
+        let body = scene.add_component(entity, body).unwrap();
+        (entity, body)
+    }
+
+    #[test]
+    fn test_resting_stack() {
+        let mut scene = scene_with_floor();
+        // a heavy box on top of light ones, dropped slightly into each other
+        let stack = [1., 1., 10.]
+            .into_iter()
+            .enumerate()
+            .map(|(i, mass)| {
+                let y = 0.45 + i as f32 * 0.95;
+                add_box(&mut scene, vec3(0., y, 0.), RigidBody::new(mass))
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..120 {
+            scene.on_update(Duration::from_millis(16)).unwrap();
+        }
+        for (i, (entity, body)) in stack.iter().enumerate() {
+            let position = scene.get_mut_transform(entity).unwrap().position_global();
+            approx::assert_abs_diff_eq!(position.x, 0., epsilon = 1e-4);
+            approx::assert_abs_diff_eq!(position.y, 0.5 + i as f32, epsilon = 0.01);
+            let velocity = scene.get_ref_component::<RigidBody>(body).unwrap().velocity;
+            approx::assert_abs_diff_eq!(velocity.y, 0., epsilon = 1e-3);
+        }
+        // the boxes rest on each other
+        let events = scene.collision_events();
+        assert!(events.iter().all(|(_, e)| e.phase == CollisionPhase::Stay));
+        assert_eq!(events.len(), 6);
+    }
+
+    #[test]
+    fn test_corrections_weighted_by_mass() {
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., 0., 0.));
+        let (light, _) = add_box(&mut scene, vec3(0., 0., 0.), RigidBody::new(1.));
+        let (heavy, _) = add_box(&mut scene, vec3(0.6, 0., 0.), RigidBody::new(3.));
+        scene.on_update(Duration::ZERO).unwrap();
+        // overlapping by 0.4: the light box moves three times as far
+        let position = scene.get_mut_transform(&light).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.x, -0.3, epsilon = 1e-4);
+        let position = scene.get_mut_transform(&heavy).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.x, 0.7, epsilon = 1e-4);
+
+        // a kinematic platform isn't pushed back, and the box it pushes is moved out of the
+        // box behind it in the passes that follow
+        let mut scene = Scene::new();
+        scene.set_gravity(vec3(0., 0., 0.));
+        let (platform, _) = add_box(
+            &mut scene,
+            vec3(0., 0., 0.),
+            RigidBody::new_kinematic(vec3(0., 0., 0.)),
+        );
+        let (pushed, _) = add_box(&mut scene, vec3(0.7, 0., 0.), RigidBody::new(1.));
+        let (behind, _) = add_box(&mut scene, vec3(1.9, 0., 0.), RigidBody::new(1.));
+        scene.on_update(Duration::ZERO).unwrap();
+        let position = scene
+            .get_mut_transform(&platform)
+            .unwrap()
+            .position_global();
+        assert_eq!(position, vec3(0., 0., 0.));
+        let position = scene.get_mut_transform(&pushed).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.x, 1., epsilon = 1e-3);
+        let position = scene.get_mut_transform(&behind).unwrap().position_global();
+        approx::assert_abs_diff_eq!(position.x, 2., epsilon = 1e-3);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use cgmath::InnerSpace;
+
+use crate::{entity::EntityId, Vector3};
+
+/// Contacts slower than this don't bounce, so that resting bodies stay at rest
+const BOUNCE_THRESHOLD: f32 = 0.5;
+
+/// Passes over the contacts of an update, so that impulses travel through stacks of bodies
+pub(crate) const VELOCITY_ITERATIONS: usize = 8;
+
+/// Motion and material of one side of a contact
+pub(crate) struct ContactBody {
+    pub velocity: Vector3,
+    pub inverse_mass: f32,
+    pub restitution: Option<f32>,
+    pub friction: Option<f32>,
+}
+
+impl ContactBody {
+    /// A collider without a body, which nothing can move
+    pub fn immovable() -> Self {
+        Self {
+            velocity: Vector3::new(0., 0., 0.),
+            inverse_mass: 0.,
+            restitution: None,
+            friction: None,
+        }
+    }
+}
+
+/// Impulse to apply to `a`, and its opposite to `b`, so that they stop moving into each other.
+/// `normal` is the unit vector pushing `a` away from `b`. None if they are already separating.
+/// Bodies bounce with the largest restitution of the two, and slide with the geometric mean
+/// of their friction
+pub(crate) fn contact_impulse(
+    a: &ContactBody,
+    b: &ContactBody,
+    normal: Vector3,
+) -> Option<Vector3> {
+    let inverse_mass = a.inverse_mass + b.inverse_mass;
+    if inverse_mass <= 0. {
+        return None;
+    }
+    let relative = a.velocity - b.velocity;
+    let normal_speed = relative.dot(normal);
+    if normal_speed >= 0. {
+        return None;
+    }
+
+    let restitution = if -normal_speed < BOUNCE_THRESHOLD {
+        0.
+    } else {
+        a.restitution.unwrap_or(0.).max(b.restitution.unwrap_or(0.))
+    };
+    let push = -(1. + restitution) * normal_speed / inverse_mass;
+    let mut impulse = normal * push;
+
+    // friction opposes sliding, up to stopping it
+    let sliding = relative - normal * normal_speed;
+    let sliding_speed = sliding.magnitude();
+    let friction = match (a.friction, b.friction) {
+        (Some(a), Some(b)) => (a * b).sqrt(),
+        (Some(f), None) | (None, Some(f)) => f,
+        (None, None) => 0.,
+    };
+    if sliding_speed > f32::EPSILON {
+        let stop = sliding_speed / inverse_mass;
+        impulse -= sliding / sliding_speed * stop.min(friction * push);
+    }
+    Some(impulse)
+}
+
+/// How many contacts away from an immovable entity each entity of the `contacts` is: 0 for
+/// the immovable ones, 1 for what rests on them, and so on up stacks. Entities out of reach of
+/// any immovable one are at `usize::MAX`
+pub(crate) fn stack_levels(
+    contacts: &[(EntityId, EntityId)],
+    immovable: impl Fn(&EntityId) -> bool,
+) -> HashMap<EntityId, usize> {
+    let mut levels = contacts
+        .iter()
+        .flat_map(|(a, b)| [*a, *b])
+        .map(|entity| {
+            let level = if immovable(&entity) { 0 } else { usize::MAX };
+            (entity, level)
+        })
+        .collect::<HashMap<_, _>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (a, b) in contacts {
+            for (from, to) in [(a, b), (b, a)] {
+                let level = levels[from].saturating_add(1);
+                if level < levels[to] {
+                    levels.insert(*to, level);
+                    changed = true;
+                }
+            }
+        }
+    }
+    levels
+}
@@ -188,12 +188,12 @@ impl MainRenderPipeline {
                 }
                 // this component is a model.
 
-                let transform_id = scene.get_transform(&entity_id);
+                let transform_id = scene.get_transform(&entity_id)?;
 
-                let (model, transform) = scene
-                    .get_mut_disjoint_2::<Model, TransformComponent>([component_id, &transform_id]);
-                let model = model.unwrap();
-                let transform = transform.unwrap();
+                let (model, transform) = scene.get_mut_disjoint_2::<Model, TransformComponent>([
+                    component_id,
+                    &transform_id,
+                ])?;
 
                 Self::draw_model(
                     model,
@@ -263,29 +263,26 @@ impl MainRenderPipeline {
             .get_mut(mesh.material)
             .ok_or(Error::Other("Invalid mesh index".to_string()))?;
 
-        let mesh_buffers = mesh.buffers_ref();
-        let mut new_instance_buffer = false;
-        let mesh_buffers = match mesh_buffers {
-            Some(buffers) => buffers,
-            None => {
-                // this buffer re-initialisation should be lazy
-                mesh.update_buffers(device);
-                new_instance_buffer = true;
-                mesh.buffers_ref().unwrap()
-            }
-        };
+        if mesh.buffers_ref().is_none() {
+            // this buffer re-initialisation should be lazy
+            mesh.update_buffers(device);
+        }
+        let mesh_buffers = mesh.buffers_mut().unwrap();
 
         if mesh_buffers.empty() {
             return Ok(());
         }
 
-        // update instance buffer (mesh's rendered transform) if it has moved
-        if transform.is_dirty() || new_instance_buffer {
-            let transform = transform.global();
-            let instance_data = [transform.to_raw()];
+        // update instance buffer (mesh's rendered transform) if it has moved since it was
+        // last written
+        let instance = transform.global().to_raw();
+        if instance != mesh_buffers.instance {
+            let instance_data = [instance];
             let data: &[u8] = bytemuck::cast_slice(&instance_data);
             queue.write_buffer(&mesh_buffers.instance_buffer, 0, data);
+            mesh_buffers.instance = instance;
         }
+        let mesh_buffers = &*mesh_buffers;
 
         // TODO Do the same thing i did with mesh: optional in buffer makes dirty bit redundant
         if material.dirty {
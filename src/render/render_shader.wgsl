@@ -22,6 +22,9 @@ struct InstanceInput {
     @location(6) model_matrix_1: vec4<f32>,
     @location(7) model_matrix_2: vec4<f32>,
     @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
 }
 
 ;
@@ -30,6 +33,7 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) global_pos: vec3<f32>,
+    @location(3) global_normal: vec3<f32>,
 }
 
 ;
@@ -37,10 +41,12 @@ struct VertexOutput {
 @vertex
 fn vs_main(model: VertexInput, instance: InstanceInput,) -> VertexOutput {
     let model_matrix = mat4x4<f32>(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3,);
+    let normal_matrix = mat3x3<f32>(instance.normal_matrix_0, instance.normal_matrix_1, instance.normal_matrix_2,);
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
     out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.pos, 1.0);
     out.global_pos = model.pos;
+    out.global_normal = normalize(normal_matrix * model.normal);
     return out;
 }
 
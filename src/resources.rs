@@ -3,7 +3,10 @@ use std::{
     path::Path,
 };
 
-use super::{error::*, model};
+use super::{
+    animation::AnimationClip, collision::TriangleMesh, error::*, model, scene::Scene,
+    serialization::ComponentRegistry,
+};
 
 pub fn load_binary(file_name: &str) -> Result<Vec<u8>> {
     // TODO make this whole PROJECT_OUT_DIR more stable and usable
@@ -22,11 +25,62 @@ pub fn load_string(file_name: &str) -> Result<String> {
     Ok(txt)
 }
 
+/// Load a scene from a RON file
+pub fn load_scene(file_name: &str, registry: &ComponentRegistry) -> Result<Scene> {
+    let text = load_string(file_name)?;
+    Scene::from_ron(&text, registry)
+}
+
+/// Save a scene to a RON file, next to the other resources
+pub fn save_scene(file_name: &str, scene: &Scene, registry: &ComponentRegistry) -> Result<()> {
+    let path = std::path::Path::new(&std::env::var("PROJECT_OUT_DIR").unwrap())
+        .join("res")
+        .join(file_name);
+    std::fs::write(path, scene.to_ron(registry)?)?;
+    Ok(())
+}
+
+/// Load an animation clip from a RON file
+pub fn load_animation(file_name: &str) -> Result<AnimationClip> {
+    let text = load_string(file_name)?;
+    AnimationClip::from_ron(&text)
+}
+
 pub fn load_image(file_name: &str) -> Result<image::DynamicImage> {
     let data = load_binary(file_name)?;
     image::load_from_memory(&data).map_err(Error::ImageError)
 }
 
+/// Load the triangles of all of the objects of an OBJ file, e.g. a low-poly version of a level
+/// to collide with. Materials and texture coordinates are ignored
+pub fn load_collision_mesh(file_name: &str) -> Result<TriangleMesh> {
+    let obj_text = load_string(file_name)?;
+    let (models, _) = tobj::load_obj_buf(
+        &mut BufReader::new(Cursor::new(obj_text)),
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+        // materials aren't needed to collide
+        |_| Err(tobj::LoadError::OpenFileFailed),
+    )?;
+
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+    for m in models {
+        let offset = vertices.len() as u32;
+        vertices.extend(m.mesh.positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]));
+        triangles.extend(
+            m.mesh
+                .indices
+                .chunks_exact(3)
+                .map(|t| [t[0] + offset, t[1] + offset, t[2] + offset]),
+        );
+    }
+    TriangleMesh::new(vertices, triangles)
+}
+
 pub async fn load_model(file_name: &str) -> Result<model::Model> {
     // path all files for the model will be relative to
     let parent_path = Path::new(file_name)
@@ -109,5 +163,9 @@ pub async fn load_model(file_name: &str) -> Result<model::Model> {
         })
         .collect::<Vec<_>>();
 
-    Ok(model::Model { meshes, materials })
+    Ok(model::Model {
+        meshes,
+        materials,
+        source: Some(file_name.to_string()),
+    })
 }
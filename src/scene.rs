@@ -1,11 +1,31 @@
-use crate::component::{Component, ComponentId, ComponentStore};
+use crate::component::{
+    schedule_updates, Component, ComponentId, ComponentStore, DynComponentRef, ParallelGroup,
+    ParallelView, UpdateStep,
+};
 use crate::entity::transform::TransformComponent;
+use crate::entity::observer::{ObserverHandle, TransformObservers};
 use crate::entity::Entity;
-use crate::event::{OnEventContext, OnStartContext, OnUpdateContext};
+use crate::event::{
+    OnCollisionContext, OnEventContext, OnStartContext, OnTriggerContext, OnUpdateContext,
+};
 use crate::model::Model;
-use crate::{Camera, Collider, CollisionArena};
+use crate::physics::{
+    contact_impulse, stack_levels, ContactBody, RigidBody, VELOCITY_ITERATIONS,
+};
+use crate::serialization::{self, ComponentRegistry};
+use crate::timer::{TimerHandle, TimerScheduler};
+use crate::transform::Transform;
+use crate::{
+    AxisAlignedBoundingBox, Camera, Collider, ColliderShape, CollisionArena, CollisionEvent,
+    CollisionLayers, CollisionPhase, Overlap, QueryFilter, Ray, RayHit, Sphere, TriggerEvent,
+    Vector3,
+};
+use cgmath::InnerSpace;
+use rayon::prelude::*;
+use std::any::TypeId;
 use std::collections::hash_map::Keys;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::TryLockResult;
 use std::time::Duration;
 
 use super::entity::EntityId;
@@ -22,11 +42,29 @@ pub struct Scene {
     component_entities: HashMap<ComponentId, EntityId>,
 
     collision: CollisionArena,
+    /// Names of the collision layers
+    collision_layers: CollisionLayers,
+    /// Acceleration of rigid bodies
+    gravity: Vector3,
+
+    timers: TimerScheduler,
+    /// Multiplier applied to the time passed to each update
+    time_scale: f32,
+
+    /// Entities whose global transform changed during the last update, in order of change
+    changed_transforms: Vec<EntityId>,
+    changed_lookup: HashSet<EntityId>,
+    transform_observers: TransformObservers,
+    /// Entities whose transform may have moved since the last propagation: every mutable
+    /// access to a transform is recorded here. Only their subtrees are propagated.
+    dirty_roots: HashSet<EntityId>,
 }
 
 pub(crate) struct Node {
     parent: Option<EntityId>,
     children: Vec<EntityId>,
+    /// The entity's transform component, cached to avoid scanning its components
+    transform: Option<ComponentId>,
     pub entity: Entity,
 }
 
@@ -39,6 +77,7 @@ impl Scene {
             Node {
                 parent: None,
                 children: vec![],
+                transform: None,
                 entity: Entity::new("root".to_string()),
             },
         );
@@ -46,6 +85,7 @@ impl Scene {
         let component_store = ComponentStore::new();
         let component_entities = HashMap::new();
         let collision = CollisionArena::new();
+        let timers = TimerScheduler::new();
 
         let mut scene = Self {
             nodes,
@@ -53,6 +93,14 @@ impl Scene {
             component_store,
             component_entities,
             collision,
+            collision_layers: CollisionLayers::new(),
+            gravity: Vector3::new(0., -9.81, 0.),
+            timers,
+            time_scale: 1.0,
+            changed_transforms: vec![],
+            changed_lookup: HashSet::new(),
+            transform_observers: TransformObservers::new(),
+            dirty_roots: HashSet::new(),
         };
 
         scene
@@ -66,110 +114,587 @@ impl Scene {
         self.root
     }
 
-    pub fn get_transform(&self, entity_id: &EntityId) -> ComponentId {
-        self.get_first_component_id_from_entity::<TransformComponent>(entity_id)
-            .expect("All entities must have transforms!") // TODO throw error
+    pub fn get_transform(&self, entity_id: &EntityId) -> Result<ComponentId> {
+        self.nodes
+            .get(entity_id)
+            .ok_or(SceneError::EntityNotFound(*entity_id))?
+            .transform
+            .clone()
+            .ok_or(SceneError::MissingTransform(*entity_id).into())
     }
 
-    pub fn get_mut_transform(&mut self, entity_id: &EntityId) -> &mut TransformComponent {
-        let id = &self.get_transform(entity_id);
-        self.get_mut_component(id)
-            .expect("All entities must have transforms!") // TODO throw error
+    pub fn get_mut_transform(&mut self, entity_id: &EntityId) -> Result<&mut TransformComponent> {
+        self.get_transform(entity_id)?;
+        self.dirty_roots.insert(*entity_id);
+        self.collision.mark_moved(*entity_id);
+        self.transform_mut(entity_id)
     }
 
-    pub fn on_start(&mut self) {
-        for (component_id, entity_id) in self.component_entities.clone() {
-            // swap component out
-            let mut component = self
-                .component_store
-                .swap(&component_id, None)
-                .expect("Component not found, scene corrupted!");
-
-            // run update
-            let _ = component.try_on_start(
-                self,
-                OnStartContext {
-                    entity: entity_id,
-                    component: component_id.clone(),
-                },
-            );
-
-            // swap component back in
-            if self
-                .component_store
-                .swap(&component_id, Some(component))
-                .is_some()
-            {
-                panic!("Component duplicate found, scene corrupted!");
-            }
+    /// Get the global transform of an entity, computed from its ancestors' local transforms.
+    /// Unlike `TransformComponent::global`, it is correct right after a move, before the move is
+    /// propagated at the end of the update.
+    pub fn global_transform(&self, entity_id: &EntityId) -> Result<Transform> {
+        let mut global = Transform::identity();
+        let mut current = Some(*entity_id);
+        while let Some(id) = current {
+            global = *self.transform_ref(&id)?.local_ref() * global;
+            current = self.parent(&id);
+        }
+        Ok(global)
+    }
+
+    /// Access a transform without marking it as moved
+    fn transform_mut(&mut self, entity_id: &EntityId) -> Result<&mut TransformComponent> {
+        let id = self.get_transform(entity_id)?;
+        self.component_store
+            .get_mut(&id)
+            .ok_or(SceneError::MissingTransform(*entity_id).into())
+    }
+
+    fn transform_ref(&self, entity_id: &EntityId) -> Result<&TransformComponent> {
+        let id = self.get_transform(entity_id)?;
+        self.component_store
+            .get_ref(&id)
+            .ok_or(SceneError::MissingTransform(*entity_id).into())
+    }
+
+    /// Record that the component may have changed, if the scene has to know: a transform may
+    /// have moved its entity, a collider has to be synced with the collision arena
+    fn mark_mutated<C: Component>(&mut self, id: &ComponentId) {
+        let Some(entity) = self.component_entities.get(id) else {
+            return;
+        };
+        if TypeId::of::<C>() == TypeId::of::<TransformComponent>() {
+            self.dirty_roots.insert(*entity);
+            self.collision.mark_moved(*entity);
+        } else if TypeId::of::<C>() == TypeId::of::<Collider>() {
+            self.collision.mark_changed(id.clone());
+            self.collision.mark_moved(*entity);
         }
     }
 
-    pub fn on_update(&mut self, delta_time: Duration) {
-        // do collider logic
-        self.collision.collider_pass(&mut self.component_store);
+    /// Get the transform of `entity_id` in the space of `relative_to`: converts points from
+    /// the entity's space to the other entity's space
+    pub fn relative_transform(
+        &self,
+        entity_id: &EntityId,
+        relative_to: &EntityId,
+    ) -> Result<Transform> {
+        Ok(self.global_transform(relative_to)?.inverse() * self.global_transform(entity_id)?)
+    }
+
+    pub fn on_start(&mut self) -> Result<()> {
+        for (component_id, entity_id) in self.ordered_components() {
+            self.run_component(&component_id, |component, scene| {
+                component.try_on_start(
+                    scene,
+                    OnStartContext {
+                        entity: entity_id,
+                        component: component_id.clone(),
+                    },
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn on_update(&mut self, delta_time: Duration) -> Result<()> {
+        let delta_time = delta_time.mul_f32(self.time_scale);
+
+        // forget the changes of the last update
+        self.changed_transforms.clear();
+        self.changed_lookup.clear();
+
+        // propagate the moves made since the last update, so colliders see current transforms
+        self.update_transforms()?;
+
+        // move rigid bodies, then do collider logic
+        self.integrate_bodies(delta_time);
+        let inverse_masses = self.body_inverse_masses();
+        let moved = self
+            .collision
+            .collider_pass(&mut self.component_store, &inverse_masses);
+        self.dirty_roots.extend(moved);
+        self.resolve_contact_velocities();
+        self.dispatch_collisions()?;
+
+        // run timers that are due
+        self.tick_timers(delta_time);
 
         // update transforms
-        self.update_transforms();
+        self.update_transforms()?;
 
-        for (component_id, entity_id) in self.component_entities.clone() {
-            // swap component out
-            let mut component = self
-                .component_store
-                .swap(&component_id, None)
-                .expect("Component not found, scene corrupted!");
-
-            // run update
-            let _ = component.try_on_update(
-                self,
-                OnUpdateContext {
-                    entity: entity_id,
-                    component: component_id.clone(),
-                    delta_time,
-                },
-            );
-
-            // swap component back in
-            if self
+        self.update_components(delta_time)?;
+
+        // propagate the moves made by components
+        self.update_transforms()?;
+
+        // clear transform dirty flags
+        self.clear_dirty_transforms()?;
+
+        self.notify_transform_observers();
+        Ok(())
+    }
+
+    pub fn on_event(&mut self, event: &winit::event::WindowEvent) -> Result<()> {
+        for (component_id, entity_id) in self.ordered_components() {
+            self.run_component(&component_id, |component, scene| {
+                component.try_on_event(
+                    scene,
+                    OnEventContext {
+                        entity: entity_id,
+                        component: component_id.clone(),
+                        event: event.into(),
+                    },
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn gravity(&self) -> Vector3 {
+        self.gravity
+    }
+
+    /// Set the acceleration of rigid bodies, which is 9.81 down the y axis by default
+    pub fn set_gravity(&mut self, gravity: Vector3) {
+        self.gravity = gravity;
+    }
+
+    /// Rigid bodies with their entity, in the order they were added
+    fn rigid_bodies(&self) -> Vec<(ComponentId, EntityId)> {
+        self.ordered_components()
+            .into_iter()
+            .filter(|(c, _)| {
+                self.component_store
+                    .get_dyn(c)
+                    .is_some_and(|c| c.type_id() == TypeId::of::<RigidBody>())
+            })
+            .collect()
+    }
+
+    /// Inverse mass of the entities with a rigid body, weighting how far collisions push them
+    fn body_inverse_masses(&self) -> HashMap<EntityId, f32> {
+        self.rigid_bodies()
+            .into_iter()
+            .filter_map(|(body, entity)| {
+                let body = self.get_ref_component::<RigidBody>(&body)?;
+                Some((entity, body.inverse_mass()))
+            })
+            .collect()
+    }
+
+    /// Accelerate the rigid bodies and move their entities by their velocity
+    fn integrate_bodies(&mut self, delta_time: Duration) {
+        let delta_time = delta_time.as_secs_f32();
+        for (body_id, entity) in self.rigid_bodies() {
+            let Ok(transform_id) = self.get_transform(&entity) else {
+                continue;
+            };
+            let Ok((body, transform)) = self
                 .component_store
-                .swap(&component_id, Some(component))
-                .is_some()
-            {
-                panic!("Component duplicate found, scene corrupted!");
+                .get_mut_disjoint_2::<RigidBody, TransformComponent>([&body_id, &transform_id])
+            else {
+                continue;
+            };
+            body.integrate(self.gravity, delta_time, transform);
+            self.dirty_roots.insert(entity);
+        }
+    }
+
+    /// Change the velocity of rigid bodies in contact, so that they bounce off and slide on
+    /// each other instead of moving further into each other
+    fn resolve_contact_velocities(&mut self) {
+        let mut contacts = self
+            .collision
+            .events()
+            .iter()
+            // each contact once
+            .filter(|(entity, c)| c.phase != CollisionPhase::Exit && *entity < c.other)
+            .map(|(entity, c)| (*entity, c.other, c.normal))
+            .collect::<Vec<_>>();
+        let bodies = contacts
+            .iter()
+            .flat_map(|(a, b, _)| [*a, *b])
+            .map(|entity| (entity, self.get_first_component_id_from_entity::<RigidBody>(&entity)))
+            .collect::<HashMap<_, _>>();
+        let pairs = contacts.iter().map(|(a, b, _)| (*a, *b)).collect::<Vec<_>>();
+        let levels = stack_levels(&pairs, |entity| {
+            bodies[entity]
+                .as_ref()
+                .and_then(|id| self.get_ref_component::<RigidBody>(id))
+                .is_none_or(|body| body.inverse_mass() == 0.)
+        });
+
+        // bodies in a stack push each other: go over the contacts, from the bottom of stacks
+        // up, until they settle
+        contacts.sort_by_key(|(a, b, _)| levels[a].min(levels[b]));
+        for contact in (0..VELOCITY_ITERATIONS).flat_map(|_| contacts.iter().copied()) {
+            self.apply_contact_impulse(&bodies, contact, None);
+        }
+        // then the lower body of each contact holds the upper one as if it was immovable,
+        // which stops what is left of the stack sinking
+        for (a, b, normal) in contacts {
+            let lower = match levels[&a].cmp(&levels[&b]) {
+                std::cmp::Ordering::Less => Some(a),
+                std::cmp::Ordering::Greater => Some(b),
+                std::cmp::Ordering::Equal => None,
+            };
+            self.apply_contact_impulse(&bodies, (a, b, normal), lower);
+        }
+    }
+
+    /// Apply the impulse of a contact to the rigid bodies of its two entities, `held` being
+    /// treated as immovable
+    fn apply_contact_impulse(
+        &mut self,
+        bodies: &HashMap<EntityId, Option<ComponentId>>,
+        (a, b, normal): (EntityId, EntityId, Vector3),
+        held: Option<EntityId>,
+    ) {
+        let side = |entity: &EntityId| {
+            let mut side = bodies[entity]
+                .as_ref()
+                .and_then(|id| self.get_ref_component::<RigidBody>(id))
+                .map_or(ContactBody::immovable(), RigidBody::contact_body);
+            if held == Some(*entity) {
+                side.inverse_mass = 0.;
+            }
+            side
+        };
+        let Some(impulse) = contact_impulse(&side(&a), &side(&b), normal) else {
+            return;
+        };
+        for (entity, impulse) in [(a, impulse), (b, -impulse)] {
+            if held == Some(entity) {
+                continue;
+            }
+            let body = bodies[&entity]
+                .as_ref()
+                .and_then(|id| self.component_store.get_mut::<RigidBody>(id));
+            if let Some(body) = body {
+                body.apply_impulse(impulse);
             }
         }
+    }
 
-        // clear transform dirty flags
-        self.clear_dirty_transforms();
+    /// Deliver the collisions and trigger overlaps of the last collider pass to the components
+    /// of their entities
+    fn dispatch_collisions(&mut self) -> Result<()> {
+        let events = self.collision.events().to_vec();
+        for (entity, collision) in events {
+            // the entity could have been removed by an earlier handler, or before the pass
+            let Some(node) = self.nodes.get(&entity) else {
+                continue;
+            };
+            for component_id in node.entity.components.clone() {
+                self.run_component(&component_id, |component, scene| {
+                    component.try_on_collision(
+                        scene,
+                        OnCollisionContext {
+                            entity,
+                            component: component_id.clone(),
+                            collision: collision.clone(),
+                        },
+                    )
+                })?;
+            }
+        }
+
+        let events = self.collision.trigger_events().to_vec();
+        for (entity, trigger) in events {
+            let Some(node) = self.nodes.get(&entity) else {
+                continue;
+            };
+            for component_id in node.entity.components.clone() {
+                self.run_component(&component_id, |component, scene| {
+                    component.try_on_trigger(
+                        scene,
+                        OnTriggerContext {
+                            entity,
+                            component: component_id.clone(),
+                            trigger: trigger.clone(),
+                        },
+                    )
+                })?;
+            }
+        }
+        Ok(())
     }
 
-    pub fn on_event(&mut self, event: &winit::event::WindowEvent) {
-        for (component_id, entity_id) in self.component_entities.clone() {
-            // swap component out
-            let mut component = self
-                .component_store
-                .swap(&component_id, None)
-                .expect("Component not found, scene corrupted!");
-
-            // run update
-            let _ = component.try_on_event(
-                self,
-                OnEventContext {
-                    entity: entity_id,
-                    component: component_id.clone(),
-                    event: event.into(),
-                },
-            );
-
-            // swap component back in
-            if self
-                .component_store
-                .swap(&component_id, Some(component))
-                .is_some()
-            {
-                panic!("Component duplicate found, scene corrupted!");
+    /// Collisions found during the last update, with the entity receiving each of them.
+    /// Every collision is listed once for each of its two entities.
+    pub fn collision_events(&self) -> &[(EntityId, CollisionEvent)] {
+        self.collision.events()
+    }
+
+    /// Trigger overlaps found during the last update, with the entity receiving each of them.
+    /// Every overlap is listed once for the trigger's entity and once for the visitor.
+    pub fn trigger_events(&self) -> &[(EntityId, TriggerEvent)] {
+        self.collision.trigger_events()
+    }
+
+    /// Entities whose colliders overlapped the triggers of `entity` during the last update
+    pub fn trigger_contents(&self, entity: &EntityId) -> Vec<EntityId> {
+        self.collision
+            .trigger_contents(entity)
+            .into_iter()
+            .filter(|e| self.nodes.contains_key(e))
+            .collect()
+    }
+
+    pub fn collision_layers(&self) -> &CollisionLayers {
+        &self.collision_layers
+    }
+
+    /// Name the layers colliders are put on, and get the masks of named layers
+    pub fn collision_layers_mut(&mut self) -> &mut CollisionLayers {
+        &mut self.collision_layers
+    }
+
+    /// First collider hit by the ray within `max_distance`
+    pub fn raycast(&self, ray: &Ray, max_distance: f32, filter: &QueryFilter) -> Option<RayHit> {
+        self.raycast_all(ray, max_distance, filter).into_iter().next()
+    }
+
+    /// Every collider hit by the ray within `max_distance`, closest first
+    pub fn raycast_all(&self, ray: &Ray, max_distance: f32, filter: &QueryFilter) -> Vec<RayHit> {
+        self.collision
+            .raycast_all(&self.component_store, ray, max_distance, filter)
+    }
+
+    /// First collider hit by `shape`, swept from `transform` along `direction` for at most
+    /// `max_distance`. The hit's distance is how far the shape can move before touching it
+    pub fn shape_cast(
+        &self,
+        shape: &ColliderShape,
+        transform: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RayHit> {
+        let direction = direction.normalize();
+        self.collision.shape_cast(
+            &self.component_store,
+            shape,
+            transform,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
+
+    /// Colliders overlapping `shape` placed at `transform`, in no particular order
+    pub fn overlap_shape(
+        &self,
+        shape: &ColliderShape,
+        transform: &Transform,
+        filter: &QueryFilter,
+    ) -> Vec<Overlap> {
+        self.collision
+            .overlap(&self.component_store, shape, transform, filter)
+    }
+
+    /// Colliders closer than `radius` to `center`, see `overlap_shape`
+    pub fn overlap_sphere(
+        &self,
+        center: Vector3,
+        radius: f32,
+        filter: &QueryFilter,
+    ) -> Vec<Overlap> {
+        let sphere = ColliderShape::Sphere(Sphere::new(center, radius));
+        self.overlap_shape(&sphere, &Transform::identity(), filter)
+    }
+
+    /// Colliders overlapping the box around `center`, aligned with the world axis, see
+    /// `overlap_shape`. Rotated boxes can be given to `overlap_shape` as an OBB
+    pub fn overlap_box(
+        &self,
+        center: Vector3,
+        half_extents: Vector3,
+        filter: &QueryFilter,
+    ) -> Vec<Overlap> {
+        let aabb = AxisAlignedBoundingBox::new(center - half_extents, center + half_extents);
+        self.overlap_shape(&ColliderShape::AABB(aabb), &Transform::identity(), filter)
+    }
+
+    /// Like `shape_cast`, but the colliders `shape` overlaps from the start aren't hit, so that
+    /// it can move out of them
+    pub(crate) fn shape_cast_from_outside(
+        &self,
+        shape: &ColliderShape,
+        transform: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RayHit> {
+        self.collision.shape_cast_from_outside(
+            &self.component_store,
+            shape,
+            transform,
+            direction.normalize(),
+            max_distance,
+            filter,
+        )
+    }
+
+    /// Update the components in the order they were added. Runs of consecutive components that
+    /// declared their access are updated on worker threads, see [`schedule_updates`]
+    fn update_components(&mut self, delta_time: Duration) -> Result<()> {
+        let components = self.ordered_components();
+        let steps = {
+            let accesses = components
+                .iter()
+                .map(|(component_id, _)| {
+                    let component = self.component_store.get_dyn(component_id)?;
+                    Some((component.type_id(), component.access()?))
+                })
+                .collect::<Vec<_>>();
+            schedule_updates(&accesses)
+        };
+
+        for step in steps {
+            match step {
+                UpdateStep::Serial(idx) => {
+                    let (component_id, entity_id) = &components[idx];
+                    self.run_component(component_id, |component, scene| {
+                        component.try_on_update(
+                            scene,
+                            OnUpdateContext {
+                                entity: *entity_id,
+                                component: component_id.clone(),
+                                delta_time,
+                            },
+                        )
+                    })?;
+                }
+                UpdateStep::Parallel(groups) => {
+                    let groups = groups
+                        .into_iter()
+                        .filter_map(|indices| {
+                            let (first, _) = &components[indices[0]];
+                            let component = self.component_store.get_dyn(first)?;
+                            Some(ParallelGroup {
+                                access: component.access()?.clone(),
+                                components: indices
+                                    .iter()
+                                    .map(|idx| components[*idx].clone())
+                                    .collect(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    self.parallel_update(&groups, delta_time)?;
+                }
             }
         }
+        Ok(())
+    }
+
+    /// Update groups of components that don't conflict at the same time, each on a worker thread
+    fn parallel_update(&mut self, groups: &[ParallelGroup], delta_time: Duration) -> Result<()> {
+        // each group takes ownership of the components it writes
+        let mut owned = groups
+            .iter()
+            .map(|group| self.component_store.take_types(group.access.writes()))
+            .collect::<Vec<_>>();
+
+        let scene = &*self;
+        let results = groups
+            .par_iter()
+            .zip(owned.par_iter_mut())
+            .map(|(group, owned)| -> Result<()> {
+                for (component_id, entity_id) in &group.components {
+                    // the component may have been removed by another one this frame
+                    if !scene.component_entities.contains_key(component_id) {
+                        continue;
+                    }
+                    let mut component = owned
+                        .remove(component_id)
+                        .ok_or(SceneError::ComponentNotFound(component_id.clone()))?;
+                    let mut view = ParallelView::new(scene, owned, &group.access);
+                    let result = component.try_on_parallel_update(
+                        &mut view,
+                        OnUpdateContext {
+                            entity: *entity_id,
+                            component: component_id.clone(),
+                            delta_time,
+                        },
+                    );
+                    owned.insert(component_id.clone(), component);
+                    result.map_err(|_| SceneError::ComponentLocked(component_id.clone()))?;
+                }
+                Ok(())
+            })
+            .collect::<Vec<_>>();
+
+        for owned in owned {
+            // transforms written by the group may have moved
+            for (component_id, component) in &owned {
+                let Some(entity) = self.component_entities.get(component_id) else {
+                    continue;
+                };
+                if component.downcast_ref::<Collider>().is_ok() {
+                    self.collision.mark_changed(component_id.clone());
+                    self.collision.mark_moved(*entity);
+                    continue;
+                }
+                let moved = component
+                    .downcast_ref::<TransformComponent>()
+                    .is_ok_and(|t| t.is_dirty());
+                if moved {
+                    self.dirty_roots.insert(*entity);
+                    self.collision.mark_moved(*entity);
+                }
+            }
+            self.component_store.put_back(owned);
+        }
+        results.into_iter().collect()
+    }
+
+    /// All components with their entity, in the order they were added
+    fn ordered_components(&self) -> Vec<(ComponentId, EntityId)> {
+        let mut components = self
+            .component_entities
+            .iter()
+            .map(|(c, e)| (c.clone(), *e))
+            .collect::<Vec<_>>();
+        components.sort_by_key(|(c, _)| self.component_store.get_dyn(c).map(|c| c.sequence()));
+        components
+    }
+
+    /// Swap a component out of the store to run `f` on it with the scene, then swap it back in
+    fn run_component<F>(&mut self, component_id: &ComponentId, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut DynComponentRef, &mut Scene) -> TryLockResult<()>,
+    {
+        // the component may have been removed by another one this frame
+        if !self.component_entities.contains_key(component_id) {
+            return Ok(());
+        }
+
+        // swap component out
+        let mut component = self
+            .component_store
+            .swap(component_id, None)
+            .ok_or(SceneError::ComponentNotFound(component_id.clone()))?;
+
+        let result = f(&mut component, self)
+            .map_err(|_| SceneError::ComponentLocked(component_id.clone()).into());
+
+        // the component removed its own entity
+        if !self.component_entities.contains_key(component_id) {
+            return result;
+        }
+
+        // swap component back in
+        if self
+            .component_store
+            .swap(component_id, Some(component))
+            .is_some()
+        {
+            return Err(SceneError::DuplicateComponent(component_id.clone()).into());
+        }
+        result
     }
 
     pub fn add_component<C: Component>(
@@ -178,19 +703,37 @@ impl Scene {
         component: C,
     ) -> Result<ComponentId> {
         if !self.nodes.contains_key(&entity) {
-            return Err(Error::Other("Entity not found!".to_string()));
+            return Err(SceneError::EntityNotFound(entity).into());
         }
+        // colliders are moved with the transform of their entity
+        let collider_transform = if TypeId::of::<C>() == TypeId::of::<Collider>() {
+            Some(self.get_transform(&entity)?)
+        } else {
+            None
+        };
 
-        let id = self.component_store.insert(component).unwrap();
+        let id = self.component_store.insert(component)?;
+        let collider = self.component_store.get_ref::<Collider>(&id);
+        if let (Some(transform), Some(collider)) = (collider_transform, collider) {
+            self.collision.add_collider(id.clone(), collider, entity, transform);
+        }
 
         self.component_entities.insert(id.clone(), entity);
 
-        let entity_node = self.nodes.get_mut(&entity).unwrap();
+        let entity_node = self
+            .nodes
+            .get_mut(&entity)
+            .ok_or(SceneError::EntityNotFound(entity))?;
         entity_node.entity.components.push(id.clone());
+        if TypeId::of::<C>() == TypeId::of::<TransformComponent>() {
+            entity_node.transform.get_or_insert(id.clone());
+            self.dirty_roots.insert(entity);
+        }
         Ok(id)
     }
 
     pub fn get_mut_component<C: Component>(&mut self, id: &ComponentId) -> Option<&mut C> {
+        self.mark_mutated::<C>(id);
         self.component_store.get_mut(id)
     }
 
@@ -199,6 +742,9 @@ impl Scene {
     }
 
     pub fn get_mut_first_component<C: Component>(&mut self) -> Option<&mut C> {
+        if let Some(id) = self.component_store.get_id_first::<C>() {
+            self.mark_mutated::<C>(&id);
+        }
         self.component_store.get_mut_first()
     }
 
@@ -220,10 +766,14 @@ impl Scene {
         None
     }
 
+    /// Borrow two different components mutably at once. Fails if the ids are the same, if a
+    /// component doesn't exist, or if it isn't of the type asked for
     pub fn get_mut_disjoint_2<C1: Component, C2: Component>(
         &mut self,
         ids: [&ComponentId; 2],
-    ) -> (Option<&mut C1>, Option<&mut C2>) {
+    ) -> Result<(&mut C1, &mut C2)> {
+        self.mark_mutated::<C1>(ids[0]);
+        self.mark_mutated::<C2>(ids[1]);
         self.component_store.get_mut_disjoint_2(ids)
     }
 
@@ -232,12 +782,15 @@ impl Scene {
         let new_node = Node {
             parent: Some(parent),
             children: vec![],
+            transform: None,
             entity: Entity::new(name),
         };
 
-        if let Some(parent_node) = self.nodes.get_mut(&parent) {
-            parent_node.children.push(id);
-        }
+        let parent_node = self
+            .nodes
+            .get_mut(&parent)
+            .ok_or(SceneError::EntityNotFound(parent))?;
+        parent_node.children.push(id);
 
         self.nodes.insert(id, new_node);
 
@@ -247,9 +800,117 @@ impl Scene {
         Ok(id)
     }
 
-    pub fn add_collider(&mut self, entity: EntityId, collider: Collider) {
-        let transform = self.get_transform(&entity);
-        self.collision.add_collider(entity, collider, transform)
+    /// Add a collider component to the entity, same as `add_component`
+    pub fn add_collider(&mut self, entity: EntityId, collider: Collider) -> Result<ComponentId> {
+        self.add_component(entity, collider)
+    }
+
+    /// Remove a component from its entity. Removing a collider removes it from the collision
+    /// arena. The transform of an entity can't be removed, only the entity itself
+    pub fn remove_component(&mut self, id: &ComponentId) -> Result<()> {
+        let entity = *self
+            .component_entities
+            .get(id)
+            .ok_or(SceneError::ComponentNotFound(id.clone()))?;
+        let node = self
+            .nodes
+            .get_mut(&entity)
+            .ok_or(SceneError::EntityNotFound(entity))?;
+        if node.transform.as_ref() == Some(id) {
+            return Err(SceneError::TransformRemoval(entity).into());
+        }
+        node.entity.components.retain(|c| c != id);
+        self.component_entities.remove(id);
+        // a component removing itself is out of the store while it runs
+        self.component_store.swap(id, None);
+        self.collision.remove_collider(id);
+        Ok(())
+    }
+
+    /// Remove an entity, its children, and all of their components from the scene.
+    /// Timers owned by the removed entities are canceled.
+    pub fn remove_entity(&mut self, entity: EntityId) -> Result<()> {
+        if entity == self.root {
+            return Err(SceneError::RootRemoval.into());
+        }
+        let node = self
+            .nodes
+            .get(&entity)
+            .ok_or(SceneError::EntityNotFound(entity))?;
+
+        // detach from parent
+        if let Some(parent) = node.parent {
+            if let Some(parent_node) = self.nodes.get_mut(&parent) {
+                parent_node.children.retain(|c| *c != entity);
+            }
+        }
+
+        let mut frontier = vec![entity];
+        while let Some(next) = frontier.pop() {
+            let Some(node) = self.nodes.remove(&next) else {
+                continue;
+            };
+            for component_id in &node.entity.components {
+                self.component_store.swap(component_id, None);
+                self.component_entities.remove(component_id);
+            }
+            self.collision.remove_entity(&next);
+            self.timers.remove_entity(&next);
+            self.transform_observers.remove_entity(&next);
+            frontier.extend(node.children);
+        }
+
+        Ok(())
+    }
+
+    /// Schedule `callback` to run once, after `delay` has passed.
+    /// The timer is canceled if `entity` is removed.
+    pub fn add_timer<F>(&mut self, entity: EntityId, delay: Duration, callback: F) -> Result<TimerHandle>
+    where
+        F: FnMut(&mut Scene, EntityId) + Send + Sync + 'static,
+    {
+        if !self.nodes.contains_key(&entity) {
+            return Err(SceneError::EntityNotFound(entity).into());
+        }
+        Ok(self.timers.schedule(entity, delay, None, Box::new(callback)))
+    }
+
+    /// Schedule `callback` to run every `interval`, until canceled.
+    /// The timer is canceled if `entity` is removed.
+    pub fn add_repeating_timer<F>(
+        &mut self,
+        entity: EntityId,
+        interval: Duration,
+        callback: F,
+    ) -> Result<TimerHandle>
+    where
+        F: FnMut(&mut Scene, EntityId) + Send + Sync + 'static,
+    {
+        if !self.nodes.contains_key(&entity) {
+            return Err(SceneError::EntityNotFound(entity).into());
+        }
+        Ok(self
+            .timers
+            .schedule(entity, interval, Some(interval), Box::new(callback)))
+    }
+
+    /// Cancel a timer. Returns false if the timer had already finished or been canceled
+    pub fn cancel_timer(&mut self, handle: TimerHandle) -> bool {
+        self.timers.cancel(handle)
+    }
+
+    pub fn is_timer_active(&self, handle: TimerHandle) -> bool {
+        self.timers.is_active(handle)
+    }
+
+    /// Set the multiplier applied to the time passed to components and timers each update.
+    /// 0 pauses the scene
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
     }
 
     pub fn get_component_entity(&self, comp_id: &ComponentId) -> Option<EntityId> {
@@ -260,63 +921,278 @@ impl Scene {
         self.nodes.get(child_id)?.parent
     }
 
-    fn update_transforms(&mut self) {
-        let mut frontier = VecDeque::new();
-        frontier.push_front(self.root);
-        // traverse transform tree from root
-        loop {
-            let next = frontier.pop_back();
-            if next.is_none() {
-                break;
+    pub fn children(&self, parent_id: &EntityId) -> Vec<EntityId> {
+        self.nodes
+            .get(parent_id)
+            .map(|n| n.children.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the name an entity was created with
+    pub fn name(&self, entity_id: &EntityId) -> Option<&str> {
+        self.nodes.get(entity_id).map(|n| n.entity.name.as_str())
+    }
+
+    /// Serialize the scene to RON. Only components registered in `registry` are saved.
+    pub fn to_ron(&self, registry: &ComponentRegistry) -> Result<String> {
+        serialization::serialize_scene(self, registry)
+    }
+
+    /// Build a scene from RON written by `Scene::to_ron`
+    pub fn from_ron(text: &str, registry: &ComponentRegistry) -> Result<Scene> {
+        serialization::deserialize_scene(text, registry)
+    }
+
+    /// The collider components of an entity, disabled ones included
+    pub(crate) fn colliders_of(&self, entity: &EntityId) -> Vec<&Collider> {
+        let Some(node) = self.nodes.get(entity) else {
+            return vec![];
+        };
+        node.entity
+            .components
+            .iter()
+            .filter_map(|id| self.component_store.get_ref::<Collider>(id))
+            .collect()
+    }
+
+    pub(crate) fn component_store(&self) -> &ComponentStore {
+        &self.component_store
+    }
+
+    /// Propagate the global transforms of the entities that moved since the last propagation
+    /// to their subtrees. Subtrees where nothing moved aren't visited.
+    fn update_transforms(&mut self) -> Result<()> {
+        if self.dirty_roots.is_empty() {
+            return Ok(());
+        }
+
+        // parents first, so children are updated from their parent's new global transform
+        let mut roots = std::mem::take(&mut self.dirty_roots)
+            .into_iter()
+            .filter_map(|e| Some((self.depth(&e)?, e)))
+            .collect::<Vec<_>>();
+        roots.sort_by_key(|(depth, _)| *depth);
+
+        let mut visited = HashSet::new();
+        for (_, root) in roots {
+            // accessed mutably, but not moved
+            if visited.contains(&root) || !self.transform_mut(&root)?.is_dirty() {
+                continue;
             }
-            let next = next.unwrap();
-            let (children, new_global) = {
-                let mut new_global = None;
 
-                let current = self.get_mut_transform(&next);
+            let mut frontier = VecDeque::new();
+            frontier.push_front(root);
+            while let Some(next) = frontier.pop_back() {
+                visited.insert(next);
+                if self.changed_lookup.insert(next) {
+                    self.changed_transforms.push(next);
+                }
+                self.collision.mark_moved(next);
 
-                let dirty = current.is_dirty();
-                if dirty {
-                    new_global = Some(current.global());
+                let global = self.transform_mut(&next)?.global();
+                let children = self
+                    .nodes
+                    .get(&next)
+                    .ok_or(SceneError::EntityNotFound(next))?
+                    .children
+                    .clone();
+                for child in children {
+                    self.transform_mut(&child)?.set_parent(global);
+                    frontier.push_front(child);
                 }
+            }
+        }
+        Ok(())
+    }
 
-                let node = self.nodes.get(&next).unwrap();
-                (node.children.clone(), new_global)
-            };
-            for child in children {
-                if let Some(new_global) = new_global {
-                    let child = self.get_mut_transform(&child);
-                    child.set_parent(new_global);
+    /// Number of ancestors of an entity, None if it isn't in the scene
+    fn depth(&self, entity: &EntityId) -> Option<usize> {
+        let mut depth = 0;
+        let mut current = self.nodes.get(entity)?.parent;
+        while let Some(id) = current {
+            depth += 1;
+            current = self.nodes.get(&id)?.parent;
+        }
+        Some(depth)
+    }
+
+    /// Run every timer that is due after `delta_time` has passed
+    fn tick_timers(&mut self, delta_time: Duration) {
+        let fired = self.timers.advance(delta_time);
+
+        for (handle, entity, count, mut callback) in fired {
+            for _ in 0..count {
+                // the timer could have been canceled, or its entity removed, by a callback
+                if !self.timers.is_active(handle) || !self.nodes.contains_key(&entity) {
+                    break;
                 }
-                frontier.push_front(child);
+                callback(self, entity);
             }
+            self.timers.restore(handle, callback);
+        }
+    }
+
+    /// Clear the dirty flags of the transforms that changed this update
+    fn clear_dirty_transforms(&mut self) -> Result<()> {
+        for entity in self.changed_transforms.clone() {
+            self.transform_mut(&entity)?.clear_dirty();
         }
+        Ok(())
     }
 
-    /// Clear the dirty flags on each transform
-    fn clear_dirty_transforms(&mut self) {
-        let mut frontier = VecDeque::new();
-        frontier.push_front(self.root);
-        // traverse transform tree from root
-        loop {
-            let next = frontier.pop_back();
-            if next.is_none() {
-                break;
+    /// Run the observers of the transforms that changed this update
+    fn notify_transform_observers(&mut self) {
+        if self.transform_observers.is_empty() {
+            return;
+        }
+        for (handle, entity, mut callback) in self.transform_observers.take(&self.changed_lookup) {
+            // the entity could have been removed by another observer
+            if let Ok(transform) = self.transform_ref(&entity) {
+                let global = transform.global();
+                callback(self, entity, global);
             }
-            let next = next.unwrap();
+            self.transform_observers.restore(handle, callback);
+        }
+    }
 
-            let current = self.get_mut_transform(&next);
+    /// Entities whose global transform changed during the last update, including those
+    /// moved because their parent moved. Entities are listed in the order they changed.
+    pub fn changed_transforms(&self) -> &[EntityId] {
+        &self.changed_transforms
+    }
+
+    /// Run `callback` at the end of every update where the global transform of `entity`
+    /// changed. The observer is removed if `entity` is removed.
+    pub fn observe_transform<F>(&mut self, entity: EntityId, callback: F) -> Result<ObserverHandle>
+    where
+        F: FnMut(&mut Scene, EntityId, Transform) + Send + Sync + 'static,
+    {
+        if !self.nodes.contains_key(&entity) {
+            return Err(SceneError::EntityNotFound(entity).into());
+        }
+        Ok(self.transform_observers.add(entity, Box::new(callback)))
+    }
+
+    /// Remove a transform observer. Returns false if it had already been removed
+    pub fn remove_transform_observer(&mut self, handle: ObserverHandle) -> bool {
+        self.transform_observers.remove(handle)
+    }
 
-            let dirty = current.is_dirty();
-            // only if transform is dirty so are its children
-            if dirty {
-                current.clear_dirty();
-                let node = self.nodes.get(&next).unwrap();
-                for child in &node.children {
-                    frontier.push_front(child.clone());
+    /// Check the consistency of the scene:
+    /// - the entity tree is connected, and parent and child links agree
+    /// - each entity has exactly one transform
+    /// - components and entities agree on who owns what
+    pub fn validate(&self) -> Result<()> {
+        // tree consistency
+        let mut reached = 0;
+        let mut frontier = vec![self.root];
+        while let Some(next) = frontier.pop() {
+            reached += 1;
+            let node = self
+                .nodes
+                .get(&next)
+                .ok_or(SceneError::EntityNotFound(next))?;
+            for child in &node.children {
+                let child_node = self
+                    .nodes
+                    .get(child)
+                    .ok_or(SceneError::EntityNotFound(*child))?;
+                if child_node.parent != Some(next) {
+                    return Err(SceneError::BrokenParentLink {
+                        child: *child,
+                        parent: next,
+                    }
+                    .into());
+                }
+                frontier.push(*child);
+            }
+        }
+        if reached != self.nodes.len() {
+            for (id, node) in &self.nodes {
+                let linked = match node.parent {
+                    Some(parent) => self
+                        .nodes
+                        .get(&parent)
+                        .is_some_and(|p| p.children.contains(id)),
+                    None => *id == self.root,
+                };
+                if !linked {
+                    return Err(SceneError::BrokenParentLink {
+                        child: *id,
+                        parent: node.parent.unwrap_or(self.root),
+                    }
+                    .into());
+                }
+            }
+            // the links agree, but form a cycle detached from the root
+            let unreachable = self
+                .nodes
+                .keys()
+                .find(|id| !self.is_descendant(id, &self.root))
+                .copied()
+                .unwrap_or(self.root);
+            return Err(SceneError::UnreachableEntity(unreachable).into());
+        }
+
+        // transforms, and component ownership
+        for (id, node) in &self.nodes {
+            let mut transforms = 0;
+            for component in &node.entity.components {
+                if self.component_entities.get(component) != Some(id) {
+                    return Err(SceneError::ComponentEntityMismatch {
+                        component: component.clone(),
+                        entity: *id,
+                    }
+                    .into());
+                }
+                if self.component_store.get_dyn(component).is_none() {
+                    return Err(SceneError::ComponentNotFound(component.clone()).into());
                 }
+                if self
+                    .get_ref_component::<TransformComponent>(component)
+                    .is_some()
+                {
+                    transforms += 1;
+                }
+            }
+            match transforms {
+                0 => return Err(SceneError::MissingTransform(*id).into()),
+                1 => (),
+                _ => return Err(SceneError::DuplicateTransform(*id).into()),
+            }
+            let cached = node
+                .transform
+                .as_ref()
+                .is_some_and(|t| node.entity.components.contains(t));
+            if !cached {
+                return Err(SceneError::MissingTransform(*id).into());
+            }
+        }
+        for (component, entity) in &self.component_entities {
+            let listed = self
+                .nodes
+                .get(entity)
+                .is_some_and(|n| n.entity.components.contains(component));
+            if !listed {
+                return Err(SceneError::OrphanComponent(component.clone()).into());
             }
         }
+
+        Ok(())
+    }
+
+    /// Is `entity` in the subtree of `ancestor`, following parent links
+    fn is_descendant(&self, entity: &EntityId, ancestor: &EntityId) -> bool {
+        let mut current = Some(*entity);
+        // bounded, in case the parent links form a cycle
+        for _ in 0..=self.nodes.len() {
+            match current {
+                Some(id) if id == *ancestor => return true,
+                Some(id) => current = self.nodes.get(&id).and_then(|n| n.parent),
+                None => return false,
+            }
+        }
+        false
     }
 
     pub(crate) fn entities(&self) -> Vec<EntityId> {
@@ -333,3 +1209,159 @@ impl Default for Scene {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_scene() {
+        let mut scene = Scene::new();
+        let a = scene.add_entity(scene.get_root(), "a".to_string()).unwrap();
+        let b = scene.add_entity(a, "b".to_string()).unwrap();
+        assert!(scene.validate().is_ok());
+
+        // parent that doesn't exist
+        let missing = EntityId::new();
+        assert!(matches!(
+            scene.add_entity(missing, "c".to_string()),
+            Err(Error::SceneError(SceneError::EntityNotFound(id))) if id == missing
+        ));
+
+        // second transform on an entity
+        scene.add_component(b, TransformComponent::new()).unwrap();
+        assert!(matches!(
+            scene.validate(),
+            Err(Error::SceneError(SceneError::DuplicateTransform(id))) if id == b
+        ));
+    }
+
+    #[test]
+    fn test_disjoint_access_errors() {
+        let mut scene = Scene::new();
+        let a = scene.add_entity(scene.get_root(), "a".to_string()).unwrap();
+        let transform = scene.get_transform(&a).unwrap();
+        let root_transform = scene.get_transform(&scene.get_root()).unwrap();
+
+        assert!(scene
+            .get_mut_disjoint_2::<TransformComponent, TransformComponent>([
+                &transform,
+                &root_transform
+            ])
+            .is_ok());
+        assert!(matches!(
+            scene.get_mut_disjoint_2::<TransformComponent, TransformComponent>([
+                &transform, &transform
+            ]),
+            Err(Error::SceneError(SceneError::SameComponentTwice(_)))
+        ));
+        assert!(matches!(
+            scene.get_mut_disjoint_2::<TransformComponent, Camera>([&transform, &root_transform]),
+            Err(Error::ComponentDowncastError)
+        ));
+        let missing = ComponentId::new();
+        assert!(matches!(
+            scene.get_mut_disjoint_2::<TransformComponent, TransformComponent>([
+                &transform, &missing
+            ]),
+            Err(Error::SceneError(SceneError::ComponentNotFound(id))) if id == missing
+        ));
+    }
+
+    #[test]
+    fn test_changed_transforms_and_observers() {
+        use cgmath::vec3;
+        use std::sync::{Arc, Mutex};
+
+        let mut scene = Scene::new();
+        let parent = scene.add_entity(scene.get_root(), "parent".to_string()).unwrap();
+        let child = scene.add_entity(parent, "child".to_string()).unwrap();
+        let other = scene.add_entity(scene.get_root(), "other".to_string()).unwrap();
+        // everything starts dirty
+        scene.on_update(Duration::ZERO).unwrap();
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_ref = seen.clone();
+        scene
+            .observe_transform(child, move |_, entity, global| {
+                seen_ref.lock().unwrap().push((entity, global.translation()));
+            })
+            .unwrap();
+
+        scene
+            .get_mut_transform(&parent)
+            .unwrap()
+            .translate_global(vec3(1., 0., 0.));
+        scene.on_update(Duration::ZERO).unwrap();
+
+        assert_eq!(scene.changed_transforms(), &[parent, child]);
+        assert!(!scene.changed_transforms().contains(&other));
+        assert_eq!(*seen.lock().unwrap(), vec![(child, vec3(1., 0., 0.))]);
+
+        // nothing moved
+        scene.on_update(Duration::ZERO).unwrap();
+        assert!(scene.changed_transforms().is_empty());
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_propagate_dirty_subtrees() {
+        use cgmath::vec3;
+
+        let mut scene = Scene::new();
+        let a = scene.add_entity(scene.get_root(), "a".to_string()).unwrap();
+        let a_child = scene.add_entity(a, "a child".to_string()).unwrap();
+        let b = scene.add_entity(scene.get_root(), "b".to_string()).unwrap();
+        let b_child = scene.add_entity(b, "b child".to_string()).unwrap();
+        scene.on_update(Duration::ZERO).unwrap();
+
+        scene
+            .get_mut_transform(&a_child)
+            .unwrap()
+            .translate_global(vec3(0., 1., 0.));
+        scene
+            .get_mut_transform(&a)
+            .unwrap()
+            .translate_global(vec3(1., 0., 0.));
+        // only read through a mutable access
+        scene.get_mut_transform(&b).unwrap();
+
+        // correct before propagation
+        let global = scene.global_transform(&a_child).unwrap();
+        assert_eq!(global.translation(), vec3(1., 1., 0.));
+
+        scene.on_update(Duration::ZERO).unwrap();
+        assert_eq!(scene.changed_transforms(), &[a, a_child]);
+        let transform = scene.get_transform(&a_child).unwrap();
+        let transform = scene
+            .get_ref_component::<TransformComponent>(&transform)
+            .unwrap();
+        assert_eq!(transform.global().translation(), vec3(1., 1., 0.));
+        assert!(!transform.is_dirty());
+        assert_eq!(
+            scene.global_transform(&b_child).unwrap().translation(),
+            vec3(0., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn test_relative_transform() {
+        use cgmath::vec3;
+
+        let mut scene = Scene::new();
+        let a = scene.add_entity(scene.get_root(), "a".to_string()).unwrap();
+        let b = scene.add_entity(scene.get_root(), "b".to_string()).unwrap();
+        scene
+            .get_mut_transform(&a)
+            .unwrap()
+            .set_position_global(vec3(1., 2., 3.));
+        scene
+            .get_mut_transform(&b)
+            .unwrap()
+            .set_position_global(vec3(-1., 0., 3.));
+
+        let relative = scene.relative_transform(&a, &b).unwrap();
+        assert_eq!(relative.translation(), vec3(2., 2., 0.));
+        assert!(scene.relative_transform(&a, &EntityId::new()).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::any::{self, TypeId};
+use std::collections::HashMap;
+
+use ron::value::RawValue;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    component::DynComponentRef, entity::EntityId, error::*, resources, transform::Transform,
+    Camera, Collider, CollisionLayers, Component, Model, Scene,
+};
+
+type SerializeFn = Box<dyn Fn(&DynComponentRef) -> Result<Box<RawValue>> + Send + Sync>;
+type DeserializeFn = Box<dyn Fn(&RawValue, &mut Scene, EntityId) -> Result<()> + Send + Sync>;
+
+struct Registration {
+    type_name: String,
+    serialize: SerializeFn,
+}
+
+/// Table of the component types that can be saved to and loaded from scene files, by name.
+/// `Camera` and `Model` are registered by default.
+pub struct ComponentRegistry {
+    by_type: HashMap<TypeId, Registration>,
+    by_name: HashMap<String, DeserializeFn>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            by_type: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+        registry.register::<Camera>("Camera");
+        // models are saved as the path they were loaded from
+        registry.register_with::<Model, _, _>(
+            "Model",
+            |model| {
+                let source = model.source.as_ref().ok_or(Error::Other(
+                    "Model was not loaded from a file and can't be saved".to_string(),
+                ))?;
+                Ok(RawValue::from_rust(source)?)
+            },
+            |raw| {
+                let source: String = raw.into_rust()?;
+                pollster::block_on(resources::load_model(&source))
+            },
+        );
+        registry
+    }
+
+    /// Register a component type with serde support under `type_name`
+    pub fn register<C: Component + Serialize + DeserializeOwned>(&mut self, type_name: &str) {
+        self.register_with::<C, _, _>(
+            type_name,
+            |c| Ok(RawValue::from_rust(c)?),
+            |raw| Ok(raw.into_rust()?),
+        );
+    }
+
+    /// Register a component type under `type_name` with custom serialize and deserialize
+    /// functions
+    pub fn register_with<C, S, D>(&mut self, type_name: &str, serialize: S, deserialize: D)
+    where
+        C: Component,
+        S: Fn(&C) -> Result<Box<RawValue>> + Send + Sync + 'static,
+        D: Fn(&RawValue) -> Result<C> + Send + Sync + 'static,
+    {
+        self.by_type.insert(
+            any::TypeId::of::<C>(),
+            Registration {
+                type_name: type_name.to_string(),
+                serialize: Box::new(move |c| serialize(c.downcast_ref::<C>()?)),
+            },
+        );
+        self.by_name.insert(
+            type_name.to_string(),
+            Box::new(move |raw, scene, entity| {
+                scene.add_component(entity, deserialize(raw)?)?;
+                Ok(())
+            }),
+        );
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneData {
+    #[serde(default, skip_serializing_if = "CollisionLayers::is_default")]
+    collision_layers: CollisionLayers,
+    root: EntityData,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntityData {
+    name: String,
+    /// Local transform
+    #[serde(default = "Transform::identity")]
+    transform: Transform,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    colliders: Vec<Collider>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    components: Vec<ComponentData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<EntityData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ComponentData {
+    #[serde(rename = "type")]
+    type_name: String,
+    data: Box<RawValue>,
+}
+
+pub(crate) fn serialize_scene(scene: &Scene, registry: &ComponentRegistry) -> Result<String> {
+    let data = SceneData {
+        collision_layers: scene.collision_layers().clone(),
+        root: serialize_entity(scene, registry, &scene.get_root())?,
+    };
+    let config = ron::ser::PrettyConfig::new().compact_arrays(true);
+    Ok(ron::ser::to_string_pretty(&data, config)?)
+}
+
+fn serialize_entity(
+    scene: &Scene,
+    registry: &ComponentRegistry,
+    entity_id: &EntityId,
+) -> Result<EntityData> {
+    let entity = scene
+        .get_entity(entity_id)
+        .ok_or(Error::Other("Entity not found!".to_string()))?;
+
+    let transform = scene
+        .get_ref_component::<crate::TransformComponent>(&scene.get_transform(entity_id)?)
+        .ok_or(Error::Other("Entity has no transform!".to_string()))?;
+
+    let mut components = vec![];
+    for component_id in &entity.components {
+        let Some(component) = scene.component_store().get_dyn(component_id) else {
+            continue;
+        };
+        // colliders are saved apart
+        if component.type_id() == TypeId::of::<Collider>() {
+            continue;
+        }
+        // unregistered components, including transforms, are not saved here
+        let Some(registration) = registry.by_type.get(&component.type_id()) else {
+            continue;
+        };
+        components.push(ComponentData {
+            type_name: registration.type_name.clone(),
+            data: (registration.serialize)(component)?,
+        });
+    }
+
+    let children = scene
+        .children(entity_id)
+        .iter()
+        .map(|child| serialize_entity(scene, registry, child))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(EntityData {
+        name: entity.name.clone(),
+        transform: *transform.local_ref(),
+        colliders: scene.colliders_of(entity_id).into_iter().cloned().collect(),
+        components,
+        children,
+    })
+}
+
+pub(crate) fn deserialize_scene(text: &str, registry: &ComponentRegistry) -> Result<Scene> {
+    let data: SceneData = ron::from_str(text)?;
+
+    let mut scene = Scene::new();
+    *scene.collision_layers_mut() = data.collision_layers;
+    let root = scene.get_root();
+    deserialize_entity(&mut scene, registry, root, data.root)?;
+    Ok(scene)
+}
+
+fn deserialize_entity(
+    scene: &mut Scene,
+    registry: &ComponentRegistry,
+    entity: EntityId,
+    data: EntityData,
+) -> Result<()> {
+    scene.get_mut_transform(&entity)?.set_local(data.transform);
+
+    for collider in data.colliders {
+        scene.add_collider(entity, collider)?;
+    }
+
+    for component in data.components {
+        let deserialize = registry
+            .by_name
+            .get(&component.type_name)
+            .ok_or(Error::UnregisteredComponent(component.type_name.clone()))?;
+        deserialize(&component.data, scene, entity)?;
+    }
+
+    for child in data.children {
+        let child_id = scene.add_entity(entity, child.name.clone())?;
+        deserialize_entity(scene, registry, child_id, child)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{OnEventContext, OnStartContext, OnUpdateContext};
+
+    #[derive(Serialize, Deserialize)]
+    struct Health {
+        points: u32,
+    }
+
+    impl Component for Health {
+        fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+        fn on_update(&mut self, _scene: &mut Scene, _context: OnUpdateContext) {}
+
+        fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+    }
+
+    #[test]
+    fn test_scene_round_trip() {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("Health");
+
+        let mut scene = Scene::new();
+        let player = scene
+            .add_entity(scene.get_root(), "player".to_string())
+            .unwrap();
+        scene.add_component(player, Health { points: 7 }).unwrap();
+        scene
+            .get_mut_transform(&player)
+            .unwrap()
+            .translate_global(vec3(1., 2., 3.));
+        scene
+            .add_collider(
+                player,
+                Collider::new_aabb(vec3(0., 0., 0.), vec3(1., 1., 1.), true),
+            )
+            .unwrap();
+        let camera = scene.add_entity(player, "camera".to_string()).unwrap();
+        scene.add_component(camera, Camera::new()).unwrap();
+
+        let text = scene.to_ron(&registry).unwrap();
+        let loaded = Scene::from_ron(&text, &registry).unwrap();
+
+        let root_children = loaded.children(&loaded.get_root());
+        assert_eq!(root_children.len(), 1);
+        let player = root_children[0];
+        assert_eq!(loaded.name(&player), Some("player"));
+        let health_id = loaded
+            .get_first_component_id_from_entity::<Health>(&player)
+            .unwrap();
+        assert_eq!(
+            loaded
+                .get_ref_component::<Health>(&health_id)
+                .unwrap()
+                .points,
+            7
+        );
+        let transform = loaded
+            .get_ref_component::<crate::TransformComponent>(&loaded.get_transform(&player).unwrap())
+            .unwrap();
+        assert_eq!(transform.local_ref().translation(), vec3(1., 2., 3.));
+        assert_eq!(loaded.colliders_of(&player).len(), 1);
+
+        let camera = loaded.children(&player)[0];
+        assert!(loaded
+            .get_first_component_id_from_entity::<Camera>(&camera)
+            .is_some());
+
+        // saving again gives the same file
+        assert_eq!(loaded.to_ron(&registry).unwrap(), text);
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+
+use crate::{entity::EntityId, scene::Scene};
+
+static NEXT_TIMER_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Closure run when a timer fires, with the scene and the entity that owns the timer
+pub type TimerCallback = Box<dyn FnMut(&mut Scene, EntityId) + Send + Sync>;
+
+/// Handle to a scheduled timer, used to cancel it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerHandle {
+    id: u32,
+}
+
+impl TimerHandle {
+    fn new() -> Self {
+        let id = NEXT_TIMER_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self { id }
+    }
+}
+
+struct Timer {
+    handle: TimerHandle,
+    entity: EntityId,
+    // time left until the timer fires
+    remaining: Duration,
+    // Some(..) for repeating timers
+    interval: Option<Duration>,
+    // None while the callback is running
+    callback: Option<TimerCallback>,
+}
+
+/// Keeps track of the timers of a scene
+pub(crate) struct TimerScheduler {
+    timers: Vec<Timer>,
+}
+
+impl TimerScheduler {
+    pub fn new() -> Self {
+        Self { timers: vec![] }
+    }
+
+    pub fn schedule(
+        &mut self,
+        entity: EntityId,
+        delay: Duration,
+        interval: Option<Duration>,
+        callback: TimerCallback,
+    ) -> TimerHandle {
+        let handle = TimerHandle::new();
+        self.timers.push(Timer {
+            handle,
+            entity,
+            remaining: delay,
+            interval,
+            callback: Some(callback),
+        });
+        handle
+    }
+
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|t| t.handle != handle);
+        self.timers.len() != len
+    }
+
+    pub fn is_active(&self, handle: TimerHandle) -> bool {
+        self.timers.iter().any(|t| t.handle == handle)
+    }
+
+    /// Remove every timer owned by the entity
+    pub fn remove_entity(&mut self, entity: &EntityId) {
+        self.timers.retain(|t| t.entity != *entity);
+    }
+
+    /// Advance all timers, returning the ones that fired along with how many times they did.
+    /// The callbacks of the returned timers are taken out, and must be given back with
+    /// `restore`
+    pub fn advance(
+        &mut self,
+        delta_time: Duration,
+    ) -> Vec<(TimerHandle, EntityId, u32, TimerCallback)> {
+        let mut fired = vec![];
+        for timer in &mut self.timers {
+            if timer.callback.is_none() {
+                continue;
+            }
+
+            let mut count = 0;
+            let mut elapsed = delta_time;
+            while elapsed >= timer.remaining {
+                elapsed -= timer.remaining;
+                count += 1;
+                match timer.interval {
+                    // a zero interval would loop forever: fire once per tick instead
+                    Some(interval) if !interval.is_zero() => timer.remaining = interval,
+                    _ => {
+                        timer.remaining = Duration::ZERO;
+                        break;
+                    }
+                }
+            }
+            if count == 0 {
+                timer.remaining -= elapsed;
+                continue;
+            }
+            if timer.interval.is_some() {
+                timer.remaining -= elapsed.min(timer.remaining);
+            }
+
+            let callback = timer.callback.take().unwrap();
+            fired.push((timer.handle, timer.entity, count, callback));
+        }
+        fired
+    }
+
+    /// Give a callback back to its timer once it has run. One-shot timers are removed.
+    pub fn restore(&mut self, handle: TimerHandle, callback: TimerCallback) {
+        if let Some(idx) = self.timers.iter().position(|t| t.handle == handle) {
+            if self.timers[idx].interval.is_some() {
+                self.timers[idx].callback = Some(callback);
+            } else {
+                self.timers.remove(idx);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_timers_fire_and_cancel() {
+        let mut scene = Scene::new();
+        let entity = scene
+            .add_entity(scene.get_root(), "timed".to_string())
+            .unwrap();
+
+        let once = Arc::new(AtomicU32::new(0));
+        let counter = once.clone();
+        scene
+            .add_timer(entity, Duration::from_millis(100), move |_, _| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+
+        let repeated = Arc::new(AtomicU32::new(0));
+        let counter = repeated.clone();
+        let handle = scene
+            .add_repeating_timer(entity, Duration::from_millis(50), move |_, _| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+
+        scene.on_update(Duration::from_millis(60)).unwrap();
+        assert_eq!(once.load(Ordering::Relaxed), 0);
+        assert_eq!(repeated.load(Ordering::Relaxed), 1);
+
+        scene.on_update(Duration::from_millis(100)).unwrap();
+        assert_eq!(once.load(Ordering::Relaxed), 1);
+        assert_eq!(repeated.load(Ordering::Relaxed), 3);
+
+        assert!(scene.cancel_timer(handle));
+        scene.on_update(Duration::from_millis(100)).unwrap();
+        assert_eq!(repeated.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_timers_removed_with_entity() {
+        let mut scene = Scene::new();
+        let entity = scene
+            .add_entity(scene.get_root(), "timed".to_string())
+            .unwrap();
+        let handle = scene
+            .add_timer(entity, Duration::from_millis(10), |_, _| {
+                panic!("should not fire")
+            })
+            .unwrap();
+
+        scene.remove_entity(entity).unwrap();
+        assert!(!scene.is_timer_active(handle));
+        scene.on_update(Duration::from_millis(20)).unwrap();
+    }
+}
@@ -1,21 +1,27 @@
-use crate::Vector3;
-#[allow(unused)]
+use crate::{Quaternion, Vector3};
 use cgmath::InnerSpace as _;
 use cgmath::{num_traits::zero, vec3};
-use cgmath::{Matrix, Matrix3, Matrix4, Rad, SquareMatrix, Zero};
+use cgmath::{Array as _, ElementWise as _, Matrix, Matrix3, Matrix4, Rad, SquareMatrix, Zero};
+use serde::{Deserialize, Serialize};
 
-/// An orthonormal transform
+/// Smallest scale along an axis: smaller scales, like 0, are clamped to it (keeping their sign)
+/// so that transforms stay invertible
+pub const MIN_SCALE: f32 = 1e-4;
+
+/// An affine transform made of a rotation, a scale and a translation
 /// +z is out of the screen
 /// Represented by a 4x4 homogenous transformation matrix:
 /// a_00    a_01    a_02    t_0
 /// a_10    a_11    a_12    t_1
 /// a_20    a_21    a_22    t_2
 /// 0       0       0       1
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Transform {
     /// Represents the top left 3x3 matrix: rotation and scale
+    #[serde(with = "crate::types::serde_array::matrix3")]
     a: cgmath::Matrix3<f32>,
     /// represents the translation
+    #[serde(with = "crate::types::serde_array::vector3")]
     t: cgmath::Vector3<f32>,
 }
 
@@ -53,9 +59,40 @@ impl Transform {
         .check_invariants()
     }
 
+    /// Construct transformation that scales by `scale` along each axis, see `MIN_SCALE`
+    pub fn from_scale(scale: Vector3) -> Self {
+        Self {
+            a: Matrix3::from_diagonal(clamp_scale(scale)),
+            t: zero(),
+        }
+        .check_invariants()
+    }
+
+    /// Construct transformation that applies the rotation
+    pub fn from_rotation(rotation: Quaternion) -> Self {
+        Self {
+            a: Matrix3::from(rotation.normalize()),
+            t: zero(),
+        }
+        .check_invariants()
+    }
+
+    /// Construct transformation that applies the scale, then the rotation, then the translation.
+    /// The scale is clamped, see `MIN_SCALE`
+    pub fn from_parts(translation: Vector3, rotation: Matrix3<f32>, scale: Vector3) -> Self {
+        Self {
+            a: rotation * Matrix3::from_diagonal(clamp_scale(scale)),
+            t: translation,
+        }
+        .check_invariants()
+    }
+
     pub fn inverse(self) -> Self {
-        // a should always be orthonormal
-        let a = self.a.transpose();
+        // a is invertible, see `check_invariants`
+        let a = self
+            .a
+            .invert()
+            .expect("`a` component of transform is not invertible");
 
         let t = a * -self.t;
         Self { a, t }.check_invariants()
@@ -77,12 +114,91 @@ impl Transform {
         self.t
     }
 
-    /// Get the scale of the transform
+    /// Get the scale of the transform. A mirrored transform has its reflection on the axis
+    /// turned the most against itself, with a negative scale, so that `rotation_matrix` stays a
+    /// proper rotation
     pub fn scale(&self) -> Vector3 {
-        vec3(
+        let mut scale = vec3(
             self.a.x.magnitude(),
             self.a.y.magnitude(),
             self.a.z.magnitude(),
+        );
+        if self.a.determinant() < 0. {
+            let facing = |i: usize| self.a[i][i] / scale[i];
+            let mirrored = (0..3)
+                .min_by(|i, j| facing(*i).total_cmp(&facing(*j)))
+                .unwrap_or(0);
+            scale[mirrored] = -scale[mirrored];
+        }
+        scale
+    }
+
+    /// Get the rotation of the transform, as an orthonormal matrix: the `a` matrix without scale.
+    /// Transforms with non-uniform scale under rotation are skewed, and have no exact rotation.
+    pub fn rotation_matrix(&self) -> Matrix3<f32> {
+        let scale = self.scale();
+        Matrix3::from_cols(self.a.x / scale.x, self.a.y / scale.y, self.a.z / scale.z)
+    }
+
+    /// Get the matrix that transforms normals: the inverse transpose of `a`
+    pub fn normal_matrix(&self) -> Matrix3<f32> {
+        self.a
+            .invert()
+            .expect("`a` component of transform is not invertible")
+            .transpose()
+    }
+
+    /// Get the rotation of the transform as a quaternion, see `rotation_matrix`
+    pub fn rotation(&self) -> Quaternion {
+        Quaternion::from(self.rotation_matrix()).normalize()
+    }
+
+    /// Apply the transform to a point: T * p
+    pub fn transform_point(&self, point: Vector3) -> Vector3 {
+        *self * point
+    }
+
+    /// Apply the transform to a direction, ignoring translation: a * d.
+    /// The direction is scaled along with the transform, and isn't normalized
+    pub fn transform_direction(&self, direction: Vector3) -> Vector3 {
+        self.a * direction
+    }
+
+    /// Apply the inverse transform to a point: T^-1 * p
+    pub fn inverse_transform_point(&self, point: Vector3) -> Vector3 {
+        self.inverse() * point
+    }
+
+    /// Apply the inverse transform to a direction, ignoring translation: a^-1 * d
+    pub fn inverse_transform_direction(&self, direction: Vector3) -> Vector3 {
+        self.inverse().transform_direction(direction)
+    }
+
+    /// Get the same transform with another translation
+    pub fn with_translation(self, translation: Vector3) -> Self {
+        Self {
+            a: self.a,
+            t: translation,
+        }
+        .check_invariants()
+    }
+
+    /// Get the same transform with another rotation, keeping the scale
+    pub fn with_rotation(self, rotation: Quaternion) -> Self {
+        Self::from_parts(self.t, Matrix3::from(rotation.normalize()), self.scale())
+    }
+
+    /// Get the same transform with another scale
+    pub fn with_scale(self, scale: Vector3) -> Self {
+        Self::from_parts(self.t, self.rotation_matrix(), scale)
+    }
+
+    /// Get the same transform with its scale multiplied by `factor`
+    pub fn with_scale_multiplied(self, factor: Vector3) -> Self {
+        Self::from_parts(
+            self.t,
+            self.rotation_matrix(),
+            self.scale().mul_element_wise(factor),
         )
     }
 
@@ -96,24 +212,20 @@ impl Transform {
     }
 
     /// Checks all the conditions that a transform must abide by:
-    /// - The 3x3 `a` matrix should be orthonormal
+    /// - The 3x3 `a` matrix should be invertible: no scale component is 0, see `MIN_SCALE`
     /// - No NaN values
     pub fn check_invariants(self) -> Self {
         #[cfg(debug_assertions)]
         {
-            if !self.a.is_finite() {
+            if !self.a.is_finite() || !self.t.is_finite() {
                 panic!("Transform should not be infinite");
             } else {
-                const EPS: f32 = 1e-3; // lets be leniant
-                if approx::abs_diff_ne!(self.a.x.dot(self.a.y), 0., epsilon = EPS)
-                    || approx::abs_diff_ne!(self.a.y.dot(self.a.z), 0., epsilon = EPS)
-                    || approx::abs_diff_ne!(self.a.z.dot(self.a.x), 0., epsilon = EPS)
-                    || approx::abs_diff_ne!(self.a.x.magnitude2(), 1., epsilon = EPS)
-                {
+                let det = self.a.determinant();
+                if det == 0. {
                     panic!(
-                    "`a` component of transform is not orthonormal: {:?}, {},{},{} should all be 0, and {} should be 1",
-                    self.a, self.a.x.dot(self.a.y), self.a.y.dot(self.a.z),self.a.z.dot(self.a.x),self.a.x.magnitude2()
-                );
+                        "`a` component of transform is not invertible: {:?}, determinant {} should not be 0",
+                        self.a, det
+                    );
                 }
             }
         }
@@ -122,6 +234,17 @@ impl Transform {
     }
 }
 
+/// Clamp each component of the scale away from 0, see `MIN_SCALE`
+fn clamp_scale(scale: Vector3) -> Vector3 {
+    scale.map(|s| {
+        if s.abs() < MIN_SCALE {
+            MIN_SCALE.copysign(s)
+        } else {
+            s
+        }
+    })
+}
+
 impl std::ops::Mul<Transform> for Transform {
     type Output = Self;
 
@@ -141,3 +264,65 @@ impl std::ops::Mul<Vector3> for Transform {
         self.a * rhs + self.t
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_inverse() {
+        let transform = Transform::from_translation(vec3(1., 2., 3.))
+            * Transform::from_angle_y(0.7)
+            * Transform::from_scale(vec3(2., 3., 0.5));
+        let point = vec3(-4., 5., 6.);
+
+        let back = transform.inverse() * (transform * point);
+        approx::assert_abs_diff_eq!(back.x, point.x, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(back.y, point.y, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(back.z, point.z, epsilon = 1e-4);
+
+        let scale = transform.scale();
+        approx::assert_abs_diff_eq!(scale.x, 2., epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(scale.y, 3., epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(scale.z, 0.5, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_zero_scale_is_clamped() {
+        let transform = Transform::from_scale(vec3(0., -0., 2.)).with_scale(vec3(0., -1., 0.));
+        let scale = transform.scale();
+        assert_eq!((scale.x, scale.y, scale.z), (MIN_SCALE, -1., MIN_SCALE));
+        assert_eq!(transform.rotation_matrix(), Matrix3::identity());
+
+        let point = vec3(1., 2., 3.);
+        let back = transform.inverse() * (transform * point);
+        approx::assert_abs_diff_eq!(back.x, point.x, epsilon = 1e-2);
+        approx::assert_abs_diff_eq!(back.y, point.y, epsilon = 1e-2);
+        approx::assert_abs_diff_eq!(back.z, point.z, epsilon = 1e-2);
+        assert!(transform.normal_matrix().is_finite());
+    }
+
+    #[test]
+    fn test_mirrored_scale() {
+        let rotation = Transform::from_angle_y(0.7);
+        let transform = rotation * Transform::from_scale(vec3(2., -3., 0.5));
+        let scale = transform.scale();
+        approx::assert_abs_diff_eq!(scale.x, 2., epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(scale.y, -3., epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(scale.z, 0.5, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(
+            transform.rotation_matrix().determinant(),
+            1.,
+            epsilon = 1e-4
+        );
+        let alignment = transform.rotation().dot(rotation.rotation());
+        approx::assert_abs_diff_eq!(alignment.abs(), 1., epsilon = 1e-4);
+
+        // the reflection goes away with the scale
+        let unmirrored = transform.with_scale(vec3(1., 1., 1.));
+        for i in 0..3 {
+            let offset = unmirrored.a[i] - rotation.a[i];
+            approx::assert_abs_diff_eq!(offset.magnitude(), 0., epsilon = 1e-4);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    animation::{AnimationValue, TrackTarget},
+    tween::Easing,
+    Quaternion, Vector3,
+};
+
+/// Interpolation of targets from one value to another. Tweens can be put one after the other
+/// in a sequence, or run together in a parallel group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tween {
+    /// Move a target from `from` to `to` over `duration` seconds
+    Value {
+        target: TrackTarget,
+        from: AnimationValue,
+        to: AnimationValue,
+        duration: f32,
+        easing: Easing,
+    },
+    /// Wait for some seconds
+    Delay(f32),
+    /// Tweens played one after the other
+    Sequence(Vec<Tween>),
+    /// Tweens played at the same time, ending with the longest
+    Parallel(Vec<Tween>),
+}
+
+impl Tween {
+    pub fn value(
+        target: TrackTarget,
+        from: AnimationValue,
+        to: AnimationValue,
+        duration: f32,
+    ) -> Self {
+        Self::Value {
+            target,
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Tween the local position of the entity
+    pub fn position(from: Vector3, to: Vector3, duration: f32) -> Self {
+        Self::value(
+            TrackTarget::Position,
+            AnimationValue::Vector(from),
+            AnimationValue::Vector(to),
+            duration,
+        )
+    }
+
+    /// Tween the local rotation of the entity, along the shortest arc
+    pub fn rotation(from: Quaternion, to: Quaternion, duration: f32) -> Self {
+        Self::value(
+            TrackTarget::Rotation,
+            AnimationValue::Rotation(from),
+            AnimationValue::Rotation(to),
+            duration,
+        )
+    }
+
+    /// Tween the local scale of the entity
+    pub fn scale(from: Vector3, to: Vector3, duration: f32) -> Self {
+        Self::value(
+            TrackTarget::Scale,
+            AnimationValue::Vector(from),
+            AnimationValue::Vector(to),
+            duration,
+        )
+    }
+
+    /// Tween a property of the tweener, read with `Tweener::property`
+    pub fn property(
+        name: impl Into<String>,
+        from: AnimationValue,
+        to: AnimationValue,
+        duration: f32,
+    ) -> Self {
+        Self::value(TrackTarget::Property(name.into()), from, to, duration)
+    }
+
+    pub fn delay(duration: f32) -> Self {
+        Self::Delay(duration)
+    }
+
+    pub fn sequence(tweens: Vec<Tween>) -> Self {
+        Self::Sequence(tweens)
+    }
+
+    pub fn parallel(tweens: Vec<Tween>) -> Self {
+        Self::Parallel(tweens)
+    }
+
+    /// Set the easing curve of a value tween, or of every value tween in a group
+    pub fn with_easing(mut self, curve: Easing) -> Self {
+        match &mut self {
+            Self::Value { easing, .. } => *easing = curve,
+            Self::Delay(_) => (),
+            Self::Sequence(tweens) | Self::Parallel(tweens) => {
+                *tweens = std::mem::take(tweens)
+                    .into_iter()
+                    .map(|t| t.with_easing(curve))
+                    .collect();
+            }
+        }
+        self
+    }
+
+    /// Total length in seconds
+    pub fn duration(&self) -> f32 {
+        match self {
+            Self::Value { duration, .. } => duration.max(0.),
+            Self::Delay(duration) => duration.max(0.),
+            Self::Sequence(tweens) => tweens.iter().map(Tween::duration).sum(),
+            Self::Parallel(tweens) => tweens.iter().map(Tween::duration).fold(0., f32::max),
+        }
+    }
+
+    /// Get the values of the targets at `time`. Tweens of a sequence that haven't started yet
+    /// don't give a value, and those that are over hold their last value.
+    pub fn sample(&self, time: f32) -> Vec<(TrackTarget, AnimationValue)> {
+        let mut values = vec![];
+        self.sample_into(time, &mut values);
+        values
+    }
+
+    fn sample_into(&self, time: f32, values: &mut Vec<(TrackTarget, AnimationValue)>) {
+        match self {
+            Self::Value {
+                target,
+                from,
+                to,
+                duration,
+                easing,
+            } => {
+                let t = if *duration > 0. { time / duration } else { 1. };
+                let value = from.lerp(to, easing.apply(t));
+                // later tweens of the same target win
+                values.retain(|(t, _)| t != target);
+                values.push((target.clone(), value));
+            }
+            Self::Delay(_) => (),
+            Self::Sequence(tweens) => {
+                let mut start = 0.;
+                for tween in tweens {
+                    if time < start {
+                        break;
+                    }
+                    tween.sample_into(time - start, values);
+                    start += tween.duration();
+                }
+            }
+            Self::Parallel(tweens) => {
+                for tween in tweens {
+                    tween.sample_into(time, values);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Easing curves, mapping the progress of a tween from 0 to 1 to the interpolation amount.
+/// `In` curves start slowly, `Out` curves end slowly, and `InOut` curves do both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    /// Goes back a little before going forward
+    BackIn,
+    BackOut,
+    BackInOut,
+    /// Overshoots and springs back
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    /// Bounces like a ball dropped on the target
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Apply the curve to `t`, clamped to [0, 1]. Always 0 at 0 and 1 at 1, but `Back` and
+    /// `Elastic` curves go outside of [0, 1] in between.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        // `InOut` curves are made of the `In` curve then the `Out` curve, each at double speed
+        let in_out = |f: fn(f32) -> f32| {
+            if t < 0.5 {
+                f(2. * t) / 2.
+            } else {
+                1. - f(2. - 2. * t) / 2.
+            }
+        };
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t) * (1. - t),
+            Easing::QuadInOut => in_out(|t| t * t),
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => in_out(|t| t * t * t),
+            Easing::SineIn => 1. - (t * PI / 2.).cos(),
+            Easing::SineOut => (t * PI / 2.).sin(),
+            Easing::SineInOut => (1. - (t * PI).cos()) / 2.,
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => 1. - expo_in(1. - t),
+            Easing::ExpoInOut => in_out(expo_in),
+            Easing::BackIn => back_in(t),
+            Easing::BackOut => 1. - back_in(1. - t),
+            Easing::BackInOut => in_out(back_in),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => 1. - elastic_in(1. - t),
+            Easing::ElasticInOut => in_out(elastic_in),
+            Easing::BounceIn => 1. - bounce_out(1. - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(|t| 1. - bounce_out(1. - t)),
+        }
+    }
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t == 0. {
+        0.
+    } else {
+        2f32.powf(10. * t - 10.)
+    }
+}
+
+fn back_in(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.70158;
+    (OVERSHOOT + 1.) * t * t * t - OVERSHOOT * t * t
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t == 0. || t == 1. {
+        return t;
+    }
+    -(2f32.powf(10. * t - 10.)) * ((t * 10. - 10.75) * (2. * PI / 3.)).sin()
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1. / D {
+        N * t * t
+    } else if t < 2. / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_endpoints() {
+        let curves = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::SineIn,
+            Easing::SineOut,
+            Easing::SineInOut,
+            Easing::ExpoIn,
+            Easing::ExpoOut,
+            Easing::ExpoInOut,
+            Easing::BackIn,
+            Easing::BackOut,
+            Easing::BackInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::ElasticInOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+            Easing::BounceInOut,
+        ];
+        for curve in curves {
+            approx::assert_abs_diff_eq!(curve.apply(0.), 0., epsilon = 1e-3);
+            approx::assert_abs_diff_eq!(curve.apply(1.), 1., epsilon = 1e-3);
+        }
+        // symmetric curves are half way at the middle
+        approx::assert_abs_diff_eq!(Easing::CubicInOut.apply(0.5), 0.5, epsilon = 1e-5);
+        approx::assert_abs_diff_eq!(Easing::SineInOut.apply(0.5), 0.5, epsilon = 1e-5);
+        assert!(Easing::QuadIn.apply(0.5) < 0.5);
+        assert!(Easing::QuadOut.apply(0.5) > 0.5);
+        assert!(Easing::BackIn.apply(0.2) < 0.);
+    }
+}
@@ -0,0 +1,8 @@
+/// Module for tweens: interpolation between two values with easing curves
+mod base;
+mod easing;
+mod tweener;
+
+pub use base::*;
+pub use easing::*;
+pub use tweener::*;
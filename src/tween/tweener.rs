@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU32;
+
+use crate::{
+    animation::{AnimationValue, TrackTarget},
+    entity::EntityId,
+    event::{OnEventContext, OnStartContext, OnUpdateContext},
+    scene::Scene,
+    tween::Tween,
+    Component,
+};
+
+static NEXT_TWEEN_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Closure run when a tween completes, with the scene and the entity of the tweener
+pub type TweenCallback = Box<dyn FnMut(&mut Scene, EntityId) + Send + Sync>;
+
+/// Handle to a playing tween, used to cancel it or to know when it completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TweenHandle {
+    id: u32,
+}
+
+impl TweenHandle {
+    fn new() -> Self {
+        let id = NEXT_TWEEN_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self { id }
+    }
+}
+
+/// How many more times a tween plays after the first time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Repeat {
+    #[default]
+    Never,
+    Times(u32),
+    Forever,
+}
+
+/// How a tween is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TweenOptions {
+    pub repeat: Repeat,
+    /// Play every other repetition backwards
+    pub yoyo: bool,
+}
+
+struct ActiveTween {
+    handle: TweenHandle,
+    tween: Tween,
+    options: TweenOptions,
+    // time since the tween started playing
+    time: f32,
+    on_complete: Option<TweenCallback>,
+}
+
+impl ActiveTween {
+    /// Number of times the tween plays, None if forever
+    fn passes(&self) -> Option<u32> {
+        match self.options.repeat {
+            Repeat::Never => Some(1),
+            Repeat::Times(n) => Some(n.saturating_add(1)),
+            Repeat::Forever => None,
+        }
+    }
+
+    /// Get the current values, and if the tween has completed
+    fn sample(&self) -> (Vec<(TrackTarget, AnimationValue)>, bool) {
+        let duration = self.tween.duration();
+        let (pass, time, completed) = match (duration > 0., self.passes()) {
+            (true, Some(passes)) if self.time >= duration * passes as f32 => {
+                (passes - 1, duration, true)
+            }
+            // instant tweens complete right away, unless they play forever
+            (false, Some(passes)) => (passes - 1, duration, true),
+            (false, None) => (0, duration, false),
+            (true, _) => {
+                let pass = (self.time / duration).floor();
+                (pass as u32, self.time - pass * duration, false)
+            }
+        };
+        let backwards = self.options.yoyo && pass % 2 == 1;
+        let time = if backwards { duration - time } else { time };
+        (self.tween.sample(time), completed)
+    }
+}
+
+/// Plays tweens on its entity: tweens drive the local transform of the entity and the tweener's
+/// properties, which other components can read.
+pub struct Tweener {
+    tweens: Vec<ActiveTween>,
+    properties: HashMap<String, AnimationValue>,
+    // tweens that completed during the last update
+    completed: Vec<TweenHandle>,
+}
+
+impl Tweener {
+    pub fn new() -> Self {
+        Self {
+            tweens: vec![],
+            properties: HashMap::new(),
+            completed: vec![],
+        }
+    }
+
+    /// Start playing a tween once. Tweens play in the order they were started, so a later tween
+    /// of the same target overrides an earlier one.
+    pub fn play(&mut self, tween: Tween) -> TweenHandle {
+        self.play_with(tween, TweenOptions::default())
+    }
+
+    pub fn play_with(&mut self, tween: Tween, options: TweenOptions) -> TweenHandle {
+        let handle = TweenHandle::new();
+        self.tweens.push(ActiveTween {
+            handle,
+            tween,
+            options,
+            time: 0.,
+            on_complete: None,
+        });
+        handle
+    }
+
+    /// Run `callback` when the tween completes. Returns false if the tween isn't playing
+    pub fn on_complete<F>(&mut self, handle: TweenHandle, callback: F) -> bool
+    where
+        F: FnMut(&mut Scene, EntityId) + Send + Sync + 'static,
+    {
+        match self.tweens.iter_mut().find(|t| t.handle == handle) {
+            Some(tween) => {
+                tween.on_complete = Some(Box::new(callback));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop a tween where it is, without completing it. Returns false if it wasn't playing
+    pub fn cancel(&mut self, handle: TweenHandle) -> bool {
+        let len = self.tweens.len();
+        self.tweens.retain(|t| t.handle != handle);
+        self.tweens.len() != len
+    }
+
+    pub fn is_playing(&self, handle: TweenHandle) -> bool {
+        self.tweens.iter().any(|t| t.handle == handle)
+    }
+
+    /// Tweens that completed during the last update
+    pub fn completed(&self) -> &[TweenHandle] {
+        &self.completed
+    }
+
+    /// Value of a property driven by a `Tween::property` tween
+    pub fn property(&self, name: &str) -> Option<&AnimationValue> {
+        self.properties.get(name)
+    }
+}
+
+impl Component for Tweener {
+    fn on_start(&mut self, _scene: &mut Scene, _context: OnStartContext) {}
+
+    fn on_update(&mut self, scene: &mut Scene, context: OnUpdateContext) {
+        self.completed.clear();
+        if self.tweens.is_empty() {
+            return;
+        }
+
+        let delta_time = context.delta_time.as_secs_f32();
+        let mut values: Vec<(TrackTarget, AnimationValue)> = vec![];
+        let mut callbacks = vec![];
+        for tween in &mut self.tweens {
+            tween.time += delta_time;
+            let (sampled, completed) = tween.sample();
+            for (target, value) in sampled {
+                values.retain(|(t, _)| *t != target);
+                values.push((target, value));
+            }
+            if completed {
+                self.completed.push(tween.handle);
+                callbacks.extend(tween.on_complete.take());
+            }
+        }
+        let completed = &self.completed;
+        self.tweens.retain(|t| !completed.contains(&t.handle));
+
+        TrackTarget::apply_values(values, scene, context.entity, &mut self.properties);
+
+        for mut callback in callbacks {
+            callback(scene, context.entity);
+        }
+    }
+
+    fn on_event(&mut self, _scene: &mut Scene, _context: OnEventContext) {}
+}
+
+impl Default for Tweener {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use cgmath::vec3;
+
+    use super::*;
+    use crate::{entity::transform::TransformComponent, tween::Easing, Vector3};
+
+    fn position(scene: &Scene, entity: &EntityId) -> Vector3 {
+        let transform = scene.get_transform(entity).unwrap();
+        scene
+            .get_ref_component::<TransformComponent>(&transform)
+            .unwrap()
+            .position_local()
+    }
+
+    #[test]
+    fn test_sequence_and_parallel() {
+        let tween = Tween::sequence(vec![
+            Tween::position(vec3(0., 0., 0.), vec3(2., 0., 0.), 1.),
+            Tween::delay(1.),
+            Tween::parallel(vec![
+                Tween::position(vec3(2., 0., 0.), vec3(2., 2., 0.), 1.),
+                Tween::property(
+                    "alpha",
+                    AnimationValue::Float(0.),
+                    AnimationValue::Float(1.),
+                    2.,
+                )
+                .with_easing(Easing::QuadIn),
+            ]),
+        ]);
+        assert_eq!(tween.duration(), 4.);
+
+        let values = tween.sample(2.5);
+        assert_eq!(
+            values,
+            vec![
+                (
+                    TrackTarget::Position,
+                    AnimationValue::Vector(vec3(2., 1., 0.))
+                ),
+                (
+                    TrackTarget::Property("alpha".to_string()),
+                    AnimationValue::Float(0.0625)
+                ),
+            ]
+        );
+        // during the delay, the first tween holds its end
+        assert_eq!(
+            tween.sample(1.5),
+            vec![(
+                TrackTarget::Position,
+                AnimationValue::Vector(vec3(2., 0., 0.))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_tweener_yoyo_and_complete() {
+        let mut scene = Scene::new();
+        let entity = scene
+            .add_entity(scene.get_root(), "door".to_string())
+            .unwrap();
+
+        let mut tweener = Tweener::new();
+        let handle = tweener.play_with(
+            Tween::position(vec3(0., 0., 0.), vec3(0., 4., 0.), 1.),
+            TweenOptions {
+                repeat: Repeat::Times(1),
+                yoyo: true,
+            },
+        );
+        let completions = Arc::new(AtomicU32::new(0));
+        let counter = completions.clone();
+        tweener.on_complete(handle, move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let tweener = scene.add_component(entity, tweener).unwrap();
+
+        scene.on_update(Duration::from_millis(500)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(0., 2., 0.));
+
+        // on the way back
+        scene.on_update(Duration::from_millis(1000)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(0., 2., 0.));
+        assert_eq!(completions.load(Ordering::Relaxed), 0);
+
+        scene.on_update(Duration::from_millis(1000)).unwrap();
+        assert_eq!(position(&scene, &entity), vec3(0., 0., 0.));
+        assert_eq!(completions.load(Ordering::Relaxed), 1);
+        let tweener = scene.get_ref_component::<Tweener>(&tweener).unwrap();
+        assert_eq!(tweener.completed(), &[handle]);
+        assert!(!tweener.is_playing(handle));
+    }
+}
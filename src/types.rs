@@ -2,5 +2,64 @@ pub type Vector3 = cgmath::Vector3<f32>;
 
 pub type Matrix4 = cgmath::Matrix4<f32>;
 
+pub type Quaternion = cgmath::Quaternion<f32>;
+
 // for Vector3::zero()
 pub use cgmath::Zero;
+
+/// Serde helpers to write cgmath types as plain arrays, which keeps scene files readable
+pub(crate) mod serde_array {
+    pub mod vector3 {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::Vector3;
+
+        pub fn serialize<S: Serializer>(v: &Vector3, serializer: S) -> Result<S::Ok, S::Error> {
+            let v: [f32; 3] = (*v).into();
+            v.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vector3, D::Error> {
+            Ok(<[f32; 3]>::deserialize(deserializer)?.into())
+        }
+    }
+
+    /// Written as [x, y, z, w]
+    pub mod quaternion {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::Quaternion;
+
+        pub fn serialize<S: Serializer>(q: &Quaternion, serializer: S) -> Result<S::Ok, S::Error> {
+            let q: [f32; 4] = (*q).into();
+            q.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Quaternion, D::Error> {
+            Ok(<[f32; 4]>::deserialize(deserializer)?.into())
+        }
+    }
+
+    pub mod matrix3 {
+        use cgmath::Matrix3;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            m: &Matrix3<f32>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            let m: [[f32; 3]; 3] = (*m).into();
+            m.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Matrix3<f32>, D::Error> {
+            Ok(<[[f32; 3]; 3]>::deserialize(deserializer)?.into())
+        }
+    }
+}